@@ -0,0 +1,203 @@
+use webrender::api::ColorF;
+use webrender::api::units::{LayoutSize, DeviceIntSize, LayoutPoint, LayoutSideOffsets};
+use gleam::gl as opengl;
+use glutin::event::{Event, WindowEvent};
+use glutin::event_loop::ControlFlow;
+use glutin::dpi::LogicalSize;
+use glutin::platform::desktop::EventLoopExtDesktop;
+use std::path::Path;
+use webrender_embed::EmbeddedUi;
+use webrender_embed::widget::{DynamicLabel, ImageDisplay};
+use webrender_embed::state::{ImmutableStore, MutableStore, Store};
+use webrender_embed::input::TextInput;
+use webrender_embed::controls::{Button, Checkbox};
+use webrender_embed::container::{Flex, Group};
+use webrender_embed::fonts::{Font, FontWeight, FontStyle};
+use webrender_embed::layout::{FlexStyle, Align};
+use luminance_glutin::GlutinSurface;
+use luminance::context::GraphicsContext;
+use luminance::pipeline::PipelineState;
+use luminance_derive::{Semantics, Vertex};
+use luminance::render_state::RenderState;
+use luminance::tess::{Mode, TessBuilder};
+use luminance::shader::{Program, BuiltProgram};
+use luminance::tess::SubTess;
+use luminance::backend::render_gate::RenderGate;
+
+const VERTEX_SHADER: &str = include_str!("vs.glsl");
+
+const FRAGMENT_SHADER: &str = include_str!("fs.glsl");
+
+const FONT_SIZE: f32 = 100.0;
+
+#[derive(Copy, Clone, Debug, Semantics)]
+pub enum VertexSemantics {
+    #[sem(name = "position", repr = "[f32; 2]", wrapper = "VertexPosition")]
+    Position,
+    #[sem(name = "color", repr = "[u8; 3]", wrapper = "VertexRGB")]
+    Color,
+}
+
+#[derive(Vertex)]
+#[vertex(sem = "VertexSemantics")]
+pub struct Vertex {
+    position: VertexPosition,
+    #[vertex(normalized = "true")]
+    color: VertexRGB,
+}
+
+const VERTICES: [Vertex; 3] = [
+    Vertex {
+        position: VertexPosition::new([-0.5, -0.5]),
+        color: VertexRGB::new([255, 0, 0]),
+    },
+    Vertex {
+        position: VertexPosition::new([0.5, -0.5]),
+        color: VertexRGB::new([0, 255, 0]),
+    },
+    Vertex {
+        position: VertexPosition::new([0., 0.5]),
+        color: VertexRGB::new([0, 0, 255]),
+    },
+];
+
+enum Message {
+    Incr
+}
+
+fn main() {
+    let (mut surface, mut el) = GlutinSurface::from_builders(
+        |win_builder| {
+            win_builder
+                .with_title("Embedded webrender")
+                .with_inner_size(LogicalSize::new(800, 600))
+        },
+        |ctx_builder| {
+            ctx_builder.with_double_buffer(Some(true))
+        },
+    ).expect("Glutin surface creation");
+
+
+    let gl = unsafe {
+        opengl::GlFns::load_with(
+            |symbol| surface.ctx.get_proc_address(symbol) as *const _
+        )
+    };
+
+    let scale_factor = surface.ctx.window().scale_factor() as f32;
+    let window_size = surface.ctx.window().inner_size();
+    let size = DeviceIntSize::new(window_size.width as i32, window_size.height as i32);
+
+    let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+    let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+
+    let state = ImmutableStore::new(0, |&s, m: Message| {
+        match m {
+            Message::Incr => s + 1
+        }
+    });
+
+    let increment_enabled = MutableStore::new(true, |s: &mut bool, value: bool| *s = value);
+    let name = MutableStore::new(String::new(), |s: &mut String, value: String| *s = value);
+
+    let mut ui = EmbeddedUi::new(gl, size, scale_factor).expect("Error creating UI");
+    if let Err(e) = ui.load_font("Open Sans", FontWeight::NORMAL, FontStyle::Normal, Path::new("OpenSans-Regular.ttf")) {
+        eprintln!("{}", e);
+    }
+
+    let label = DynamicLabel::new(state.selector(|s| s.to_string()), Font::new("Open Sans", FONT_SIZE), LayoutPoint::new(0.0, 0.0), red);
+    let mut controls = Flex::new(FlexStyle {
+        padding: LayoutSideOffsets::new_all_same(10.0),
+        gap: 10.0,
+        align_items: Align::Center,
+        ..FlexStyle::default()
+    });
+    controls.push(label);
+    let mut increment = Button::new("Increment", Font::new("Open Sans", 24.0), state.dispatcher(|()| Message::Incr), LayoutPoint::zero());
+    increment.set_enabled(increment_enabled.selector(|&enabled| enabled));
+    controls.push(increment);
+    controls.push(Checkbox::new(increment_enabled.selector(|&enabled| enabled), increment_enabled.dispatcher(|value| value), "Enable increment", Font::new("Open Sans", 24.0), LayoutPoint::zero()));
+    controls.push(TextInput::new(name.selector(|s| s.clone()), name.dispatcher(|value| value), Font::new("Open Sans", 24.0), LayoutPoint::zero(), 200.0, ColorF::WHITE));
+    // The controls are laid out in a column, the image is placed on top of them at its own position
+    let mut root = Group::new(Vec::new());
+    root.push(controls);
+    match ui.load_image(Path::new("planet.png")) {
+        Ok(planet) => root.push(ImageDisplay::new(planet, LayoutPoint::new(200.0, 200.0), LayoutSize::new(100.0, 100.0))),
+        Err(e) => eprintln!("{}", e)
+    }
+    ui.set_root(root);
+
+    let mut backbuffer = surface.back_buffer().expect("Error loading backbuffer");
+
+    let triangle = TessBuilder::new(&mut surface)
+        .unwrap()
+        .add_vertices(VERTICES)
+        .unwrap()
+        .set_mode(Mode::Triangle)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let program: BuiltProgram<_, VertexSemantics, (), ()> = Program::from_strings(&mut surface, VERTEX_SHADER, None, None, FRAGMENT_SHADER).unwrap();
+    for warn in &program.warnings {
+        println!("{}", warn);
+    }
+
+    let mut program = program.ignore_warnings();
+
+    el.run_return(|event, _target, control_flow| {
+        let next_frame_time = std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
+        *control_flow = ControlFlow::WaitUntil(next_frame_time);
+
+        match event {
+            Event::WindowEvent { window_id: _, event } => {
+                match &event {
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit
+                    }
+                    WindowEvent::Resized(new_size) => {
+                        surface.ctx.resize(*new_size);
+                        backbuffer = surface.back_buffer().expect("Error loading backbuffer");
+                    }
+                    WindowEvent::ScaleFactorChanged { scale_factor: _, new_inner_size } => {
+                        surface.ctx.resize(**new_inner_size);
+                        backbuffer = surface.back_buffer().expect("Error loading backbuffer");
+                    }
+                    _ => ()
+                }
+                ui.handle_window_event(&event);
+            }
+            Event::DeviceEvent { device_id: _, event } => {
+                ui.handle_device_event(&event);
+            }
+            _ => ()
+        }
+
+        if let Err(e) = ui.update() {
+            eprintln!("{}", e);
+        }
+        if let Some(position) = ui.ime_position() {
+            surface.ctx.window().set_ime_position(position);
+        }
+
+        surface
+            .pipeline_gate()
+            .pipeline(&backbuffer,
+                      &PipelineState::default().set_clear_color(blue.to_array()),
+                      |_, mut sh| {
+                          sh.shade(&mut program, |_, _, mut rend| {
+                              rend.render(&RenderState::default(), |mut tess| {
+                                  tess.render(triangle.slice(..).unwrap())
+                              })
+                          })
+                      });
+
+        if let Err(e) = ui.render_into_current_framebuffer() {
+            eprintln!("{}", e);
+        }
+
+        surface.swap_buffers();
+    });
+
+    ui.deinit();
+}
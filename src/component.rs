@@ -1,5 +1,17 @@
 pub trait Component<DrawCtx, RenderData, UpdateCtx, Event> {
     fn draw(&self, ctx: &mut DrawCtx, render_data: &RenderData);
     fn update(&mut self, ctx: &mut UpdateCtx);
-    fn handle_event(&mut self, event: Event);
+    // Handles an event meant for this component itself, returns true if the event was consumed
+    fn handle_event(&mut self, event: Event) -> bool;
+
+    // Delivers an event to the descendant at the given path.
+    // Containers pass it on to their child at path[0] and let it bubble up to their own handle_event if the child does not consume it.
+    fn handle_routed_event(&mut self, path: &[usize], event: Event) -> bool {
+        path.is_empty() && self.handle_event(event)
+    }
+
+    // Delivers an event to every component in the tree, containers pass it on to all of their children
+    fn broadcast_event(&mut self, event: Event) -> bool {
+        self.handle_event(event)
+    }
 }
\ No newline at end of file
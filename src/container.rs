@@ -0,0 +1,227 @@
+use webrender::api::*;
+use webrender::api::units::*;
+use crate::component::Component;
+use crate::widget::*;
+use crate::layout::{self, Layout, FlexStyle, FlexItemStyle, FlexItem};
+
+fn draw_children(children: &[BoxedWidget], ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+    for (index, child) in children.iter().enumerate() {
+        ctx.hit_tags.enter(index);
+        child.draw(ctx, render_data);
+        ctx.hit_tags.leave();
+    }
+}
+
+fn update_children(children: &mut [BoxedWidget], ctx: &mut WebrenderUpdateContext) {
+    for child in children {
+        child.update(ctx);
+    }
+}
+
+// Every child receives the event, it counts as consumed if any of them consumed it
+fn broadcast_event(children: &mut [BoxedWidget], event: WebrenderEvent) -> bool {
+    let mut consumed = false;
+    for child in children {
+        consumed |= child.broadcast_event(event.clone());
+    }
+    consumed
+}
+
+// Overlays place every child at its own position with the size it asks for and measure large enough to contain all of them
+fn measure_overlay(children: &[BoxedWidget]) -> LayoutSize {
+    children.iter().fold(LayoutSize::zero(), |size, child| {
+        let extent = child.position() + child.measure();
+        LayoutSize::new(f32::max(size.width, extent.x), f32::max(size.height, extent.y))
+    })
+}
+
+fn arrange_overlay(children: &mut [BoxedWidget], bounds: LayoutRect) {
+    for child in children {
+        let rect = LayoutRect::new(bounds.origin + child.position().to_vector(), child.measure());
+        child.arrange(rect);
+    }
+}
+
+fn route_event(children: &mut [BoxedWidget], path: &[usize], event: WebrenderEvent) -> bool {
+    match path.split_first() {
+        Some((&index, rest)) => {
+            match children.get_mut(index) {
+                Some(child) => child.handle_routed_event(rest, event),
+                None => false
+            }
+        }
+        None => false
+    }
+}
+
+// Draws its children in order, without affecting how they are composited
+pub struct Group<'a> {
+    children: Vec<BoxedWidget<'a>>
+}
+
+impl<'a> Group<'a> {
+    pub fn new(children: Vec<BoxedWidget<'a>>) -> Self {
+        Group {
+            children
+        }
+    }
+
+    pub fn push<W>(&mut self, child: W) where W: Widget + 'a {
+        self.children.push(Box::new(child));
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Group<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        draw_children(&self.children, ctx, render_data);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        update_children(&mut self.children, ctx);
+    }
+
+    // The container does not react to events itself
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+
+    fn handle_routed_event(&mut self, path: &[usize], event: WebrenderEvent) -> bool {
+        route_event(&mut self.children, path, event.clone()) || self.handle_event(event)
+    }
+
+    fn broadcast_event(&mut self, event: WebrenderEvent) -> bool {
+        broadcast_event(&mut self.children, event)
+    }
+}
+
+impl<'a> Layout for Group<'a> {
+    fn measure(&self) -> LayoutSize {
+        measure_overlay(&self.children)
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        arrange_overlay(&mut self.children, bounds);
+    }
+}
+
+// Draws its children into their own stacking context, so they are composited as a single layer
+pub struct Stack<'a> {
+    children: Vec<BoxedWidget<'a>>
+}
+
+impl<'a> Stack<'a> {
+    pub fn new(children: Vec<BoxedWidget<'a>>) -> Self {
+        Stack {
+            children
+        }
+    }
+
+    pub fn push<W>(&mut self, child: W) where W: Widget + 'a {
+        self.children.push(Box::new(child));
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Stack<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        ctx.builder.push_simple_stacking_context(LayoutPoint::zero(), render_data.space_clip().spatial_id, PrimitiveFlags::IS_BACKFACE_VISIBLE);
+        draw_children(&self.children, ctx, render_data);
+        ctx.builder.pop_stacking_context();
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        update_children(&mut self.children, ctx);
+    }
+
+    // The container does not react to events itself
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+
+    fn handle_routed_event(&mut self, path: &[usize], event: WebrenderEvent) -> bool {
+        route_event(&mut self.children, path, event.clone()) || self.handle_event(event)
+    }
+
+    fn broadcast_event(&mut self, event: WebrenderEvent) -> bool {
+        broadcast_event(&mut self.children, event)
+    }
+}
+
+impl<'a> Layout for Stack<'a> {
+    fn measure(&self) -> LayoutSize {
+        measure_overlay(&self.children)
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        arrange_overlay(&mut self.children, bounds);
+    }
+}
+
+// Lays out its children in a row or column, see layout::arrange
+pub struct Flex<'a> {
+    style: FlexStyle,
+    item_styles: Vec<FlexItemStyle>,
+    children: Vec<BoxedWidget<'a>>
+}
+
+impl<'a> Flex<'a> {
+    pub fn new(style: FlexStyle) -> Self {
+        Flex {
+            style,
+            item_styles: Vec::new(),
+            children: Vec::new()
+        }
+    }
+
+    pub fn push<W>(&mut self, child: W) where W: Widget + 'a {
+        self.push_with_style(child, FlexItemStyle::default());
+    }
+
+    pub fn push_with_style<W>(&mut self, child: W, style: FlexItemStyle) where W: Widget + 'a {
+        self.item_styles.push(style);
+        self.children.push(Box::new(child));
+    }
+
+    fn items(&self) -> Vec<FlexItem> {
+        self.children
+            .iter()
+            .zip(&self.item_styles)
+            .map(|(child, &style)| FlexItem::new(child.measure(), style))
+            .collect()
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Flex<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        draw_children(&self.children, ctx, render_data);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        update_children(&mut self.children, ctx);
+    }
+
+    // The container does not react to events itself
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+
+    fn handle_routed_event(&mut self, path: &[usize], event: WebrenderEvent) -> bool {
+        route_event(&mut self.children, path, event.clone()) || self.handle_event(event)
+    }
+
+    fn broadcast_event(&mut self, event: WebrenderEvent) -> bool {
+        broadcast_event(&mut self.children, event)
+    }
+}
+
+impl<'a> Layout for Flex<'a> {
+    fn measure(&self) -> LayoutSize {
+        layout::measure(&self.style, &self.items())
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        let rects = layout::arrange(&self.style, bounds, &self.items());
+        for (child, rect) in self.children.iter_mut().zip(rects) {
+            child.arrange(rect);
+        }
+    }
+}
\ No newline at end of file
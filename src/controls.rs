@@ -0,0 +1,663 @@
+use webrender::api::*;
+use webrender::api::units::*;
+use glutin::event::{MouseButton, VirtualKeyCode};
+use crate::component::Component;
+use crate::state::{Selector, Dispatcher};
+use crate::layout::Layout;
+use crate::text::{ParagraphStyle, TextAlign, VerticalAlign};
+use crate::fonts::Font;
+use crate::widget::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ButtonState {
+    Normal,
+    Hovered,
+    Pressed,
+    Disabled
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonColors {
+    pub background: ColorF,
+    pub border: ColorF,
+    pub text: ColorF
+}
+
+impl ButtonColors {
+    pub fn new(background: ColorF, border: ColorF, text: ColorF) -> Self {
+        ButtonColors {
+            background,
+            border,
+            text
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonStyle {
+    pub normal: ButtonColors,
+    pub hovered: ButtonColors,
+    pub pressed: ButtonColors,
+    pub disabled: ButtonColors,
+    pub border_width: f32,
+    // Space between the border and the label
+    pub padding: LayoutSideOffsets
+}
+
+impl ButtonStyle {
+    pub fn colors(&self, state: ButtonState) -> ButtonColors {
+        match state {
+            ButtonState::Normal => self.normal,
+            ButtonState::Hovered => self.hovered,
+            ButtonState::Pressed => self.pressed,
+            ButtonState::Disabled => self.disabled
+        }
+    }
+}
+
+impl Default for ButtonStyle {
+    fn default() -> Self {
+        let border = ColorF::new(0.4, 0.4, 0.4, 1.0);
+        ButtonStyle {
+            normal: ButtonColors::new(ColorF::new(0.9, 0.9, 0.9, 1.0), border, ColorF::BLACK),
+            hovered: ButtonColors::new(ColorF::new(0.95, 0.95, 0.95, 1.0), border, ColorF::BLACK),
+            pressed: ButtonColors::new(ColorF::new(0.75, 0.75, 0.75, 1.0), border, ColorF::BLACK),
+            disabled: ButtonColors::new(ColorF::new(0.85, 0.85, 0.85, 1.0), ColorF::new(0.7, 0.7, 0.7, 1.0), ColorF::new(0.5, 0.5, 0.5, 1.0)),
+            border_width: 1.0,
+            padding: LayoutSideOffsets::new(4.0, 8.0, 4.0, 8.0)
+        }
+    }
+}
+
+fn push_solid_border(ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData, bounds: LayoutRect, width: f32, color: ColorF) {
+    if width <= 0.0 {
+        return
+    }
+
+    let side = BorderSide {
+        color,
+        style: BorderStyle::Solid
+    };
+    let details = BorderDetails::Normal(NormalBorder {
+        left: side,
+        right: side,
+        top: side,
+        bottom: side,
+        radius: BorderRadius::zero(),
+        do_aa: true
+    });
+    let mut info = CommonItemProperties::new(bounds, render_data.space_clip());
+    info.hit_info = Some(ctx.hit_tag());
+    ctx.builder.push_border(&info, bounds, LayoutSideOffsets::new_all_same(width), details);
+}
+
+// Shrinks the rect by the offsets on every side
+fn inset(rect: LayoutRect, offsets: LayoutSideOffsets) -> LayoutRect {
+    let origin = rect.origin + LayoutVector2D::new(offsets.left, offsets.top);
+    let size = LayoutSize::new(f32::max(rect.size.width - offsets.horizontal(), 0.0), f32::max(rect.size.height - offsets.vertical(), 0.0));
+    LayoutRect::new(origin, size)
+}
+
+// Pointer and keyboard state shared by the controls
+#[derive(Clone, Copy, Debug, Default)]
+struct Interaction {
+    hovered: bool,
+    pressed: bool,
+    focused: bool
+}
+
+impl Interaction {
+    // Returns None if the event is not meant for the control, otherwise whether it activated the control.
+    // A click only activates if the pointer is released over the control, space and enter activate it while it is focused.
+    fn handle(&mut self, event: &WebrenderEvent, enabled: bool) -> Option<bool> {
+        match event {
+            WebrenderEvent::PointerEntered => self.hovered = true,
+            WebrenderEvent::PointerLeft => self.hovered = false,
+            WebrenderEvent::FocusGained => self.focused = true,
+            WebrenderEvent::FocusLost => self.focused = false,
+            WebrenderEvent::PointerPressed { button: MouseButton::Left, .. } if enabled => self.pressed = true,
+            WebrenderEvent::PointerReleased { button: MouseButton::Left, .. } if self.pressed => {
+                self.pressed = false;
+                return Some(self.hovered && enabled)
+            }
+            WebrenderEvent::KeyDown { key: VirtualKeyCode::Space, .. } | WebrenderEvent::KeyDown { key: VirtualKeyCode::Return, .. } if enabled && self.focused => {
+                return Some(true)
+            }
+            _ => return None
+        }
+        Some(false)
+    }
+}
+
+// A background and border around a centered label, that dispatches its message when it is clicked
+// or activated with space or enter while focused. The click only counts if the pointer is released over the button.
+pub struct Button<'a> {
+    background: Rect,
+    label: StaticLabel,
+    style: ButtonStyle,
+    on_click: Dispatcher<'a, ()>,
+    enabled: Selector<'a, bool>,
+    // Where it asks to be placed when it is laid out
+    position: LayoutPoint,
+    area: LayoutRect,
+    state: ButtonState,
+    interaction: Interaction
+}
+
+impl<'a> Button<'a> {
+    pub fn new(content: &str, font: Font, on_click: Dispatcher<'a, ()>, position: LayoutPoint) -> Self {
+        Button::with_style(content, font, ButtonStyle::default(), on_click, position)
+    }
+
+    pub fn with_style(content: &str, font: Font, style: ButtonStyle, on_click: Dispatcher<'a, ()>, position: LayoutPoint) -> Self {
+        let label_style = ParagraphStyle {
+            align: TextAlign::Center,
+            vertical_align: VerticalAlign::Middle,
+            ..ParagraphStyle::default()
+        };
+        let area = LayoutRect::new(position, LayoutSize::zero());
+        Button {
+            background: Rect::new(area, style.normal.background),
+            label: StaticLabel::with_style(content, font, label_style, position, style.normal.text),
+            style,
+            on_click,
+            enabled: Box::new(|| true),
+            position,
+            area,
+            state: ButtonState::Normal,
+            interaction: Interaction::default()
+        }
+    }
+
+    // The button is disabled while the selector returns false
+    pub fn set_enabled(&mut self, enabled: Selector<'a, bool>) {
+        self.enabled = enabled;
+    }
+
+    pub fn state(&self) -> ButtonState {
+        self.state
+    }
+
+    fn set_state(&mut self, state: ButtonState) {
+        self.state = state;
+        let colors = self.style.colors(state);
+        self.background.set_color(colors.background);
+        self.label.set_color(colors.text);
+    }
+
+    fn refresh(&mut self) {
+        let state = if self.state == ButtonState::Disabled {
+            ButtonState::Disabled
+        } else if self.interaction.pressed && self.interaction.hovered {
+            ButtonState::Pressed
+        } else if self.interaction.hovered {
+            ButtonState::Hovered
+        } else {
+            ButtonState::Normal
+        };
+        self.set_state(state);
+    }
+
+    fn click(&self) {
+        (self.on_click)(());
+    }
+
+    fn padding(&self) -> LayoutSideOffsets {
+        self.style.padding + LayoutSideOffsets::new_all_same(self.style.border_width)
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Button<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        self.background.draw(ctx, render_data);
+        push_solid_border(ctx, render_data, self.area, self.style.border_width, self.style.colors(self.state).border);
+        self.label.draw(ctx, render_data);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        self.label.update(ctx);
+        if (self.enabled)() {
+            if self.state == ButtonState::Disabled {
+                self.state = ButtonState::Normal;
+            }
+            self.refresh();
+        } else {
+            self.interaction.pressed = false;
+            self.set_state(ButtonState::Disabled);
+        }
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        let enabled = self.state != ButtonState::Disabled;
+        let activated = match self.interaction.handle(&event, enabled) {
+            Some(activated) => activated,
+            None => return false
+        };
+        if activated {
+            self.click();
+        }
+        if enabled {
+            self.refresh();
+        }
+        true
+    }
+}
+
+impl<'a> Layout for Button<'a> {
+    fn measure(&self) -> LayoutSize {
+        let padding = self.padding();
+        self.label.measure() + LayoutSize::new(padding.horizontal(), padding.vertical())
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
+        self.background.arrange(bounds);
+        self.label.arrange(inset(bounds, self.padding()));
+    }
+}
+
+const INDICATOR_GAP: f32 = 6.0;
+const RADIO_ROW_GAP: f32 = 4.0;
+
+// Colors of the checkbox, radio button and toggle indicators
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToggleStyle {
+    pub background: ColorF,
+    pub border: ColorF,
+    // The check mark, selected radio dot and the track of a toggle that is on
+    pub accent: ColorF,
+    pub hover_border: ColorF,
+    pub text: ColorF
+}
+
+impl Default for ToggleStyle {
+    fn default() -> Self {
+        ToggleStyle {
+            background: ColorF::WHITE,
+            border: ColorF::new(0.4, 0.4, 0.4, 1.0),
+            accent: ColorF::new(0.2, 0.4, 1.0, 1.0),
+            hover_border: ColorF::new(0.2, 0.4, 1.0, 1.0),
+            text: ColorF::BLACK
+        }
+    }
+}
+
+impl ToggleStyle {
+    fn border(&self, interaction: &Interaction) -> ColorF {
+        if interaction.hovered || interaction.focused {
+            self.hover_border
+        } else {
+            self.border
+        }
+    }
+}
+
+fn indicator_label(content: &str, font: Font, position: LayoutPoint, color: ColorF) -> StaticLabel {
+    let style = ParagraphStyle {
+        vertical_align: VerticalAlign::Middle,
+        ..ParagraphStyle::default()
+    };
+    StaticLabel::with_style(content, font, style, position, color)
+}
+
+// Draws a rect with rounded corners by clipping it
+fn push_rounded_rect(ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData, rect: LayoutRect, radius: f32, color: ColorF) {
+    let parent = render_data.space_clip();
+    let region = ComplexClipRegion::new(rect, BorderRadius::uniform(radius), ClipMode::Clip);
+    let clip_id = ctx.builder.define_clip(&parent, rect, vec![region], None);
+    let space_clip = SpaceAndClipInfo {
+        spatial_id: parent.spatial_id,
+        clip_id
+    };
+    ctx.builder.push_rect(&CommonItemProperties::new(rect, space_clip), color);
+}
+
+// A rounded box with a one pixel border
+fn push_indicator_box(ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData, rect: LayoutRect, radius: f32, border: ColorF, background: ColorF) {
+    push_rounded_rect(ctx, render_data, rect, radius, border);
+    push_rounded_rect(ctx, render_data, inset(rect, LayoutSideOffsets::new_all_same(1.0)), f32::max(radius - 1.0, 0.0), background);
+}
+
+// Places an indicator of the given size in front of a label, both vertically centered in the bounds
+fn arrange_row(bounds: LayoutRect, indicator: LayoutSize, label: &mut StaticLabel) -> LayoutRect {
+    let y = bounds.origin.y + (bounds.size.height - indicator.height) / 2.0;
+    let offset = indicator.width + INDICATOR_GAP;
+    label.arrange(LayoutRect::new(bounds.origin + LayoutVector2D::new(offset, 0.0), LayoutSize::new(f32::max(bounds.size.width - offset, 0.0), bounds.size.height)));
+    LayoutRect::new(LayoutPoint::new(bounds.origin.x, y), indicator)
+}
+
+fn measure_row(indicator: LayoutSize, label: &StaticLabel) -> LayoutSize {
+    let label = label.measure();
+    LayoutSize::new(indicator.width + INDICATOR_GAP + label.width, f32::max(indicator.height, label.height))
+}
+
+// A box in front of a label that is checked while the selector returns true.
+// Clicking it dispatches the opposite of the current value.
+pub struct Checkbox<'a> {
+    checked: Selector<'a, bool>,
+    on_change: Dispatcher<'a, bool>,
+    label: StaticLabel,
+    style: ToggleStyle,
+    // The size of the box
+    size: f32,
+    // Where it asks to be placed when it is laid out
+    position: LayoutPoint,
+    indicator: LayoutRect,
+    value: bool,
+    interaction: Interaction
+}
+
+impl<'a> Checkbox<'a> {
+    pub fn new(checked: Selector<'a, bool>, on_change: Dispatcher<'a, bool>, content: &str, font: Font, position: LayoutPoint) -> Self {
+        Checkbox::with_style(checked, on_change, content, font, ToggleStyle::default(), position)
+    }
+
+    pub fn with_style(checked: Selector<'a, bool>, on_change: Dispatcher<'a, bool>, content: &str, font: Font, style: ToggleStyle, position: LayoutPoint) -> Self {
+        let size = font.size;
+        Checkbox {
+            checked,
+            on_change,
+            label: indicator_label(content, font, position, style.text),
+            style,
+            size,
+            position,
+            indicator: LayoutRect::new(position, LayoutSize::new(size, size)),
+            value: false,
+            interaction: Interaction::default()
+        }
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Checkbox<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let mut info = CommonItemProperties::new(self.indicator, render_data.space_clip());
+        info.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_hit_test(&info);
+
+        push_indicator_box(ctx, render_data, self.indicator, 2.0, self.style.border(&self.interaction), self.style.background);
+        if self.value {
+            let mark = inset(self.indicator, LayoutSideOffsets::new_all_same(self.size / 4.0));
+            push_rounded_rect(ctx, render_data, mark, 1.0, self.style.accent);
+        }
+        self.label.draw(ctx, render_data);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        self.value = (self.checked)();
+        self.label.update(ctx);
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        match self.interaction.handle(&event, true) {
+            Some(true) => (self.on_change)(!self.value),
+            Some(false) => (),
+            None => return false
+        }
+        true
+    }
+}
+
+impl<'a> Layout for Checkbox<'a> {
+    fn measure(&self) -> LayoutSize {
+        measure_row(LayoutSize::new(self.size, self.size), &self.label)
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.indicator = arrange_row(bounds, LayoutSize::new(self.size, self.size), &mut self.label);
+    }
+}
+
+// A switch with a sliding knob in front of a label, on while the selector returns true.
+// Clicking it dispatches the opposite of the current value.
+pub struct Toggle<'a> {
+    on: Selector<'a, bool>,
+    on_change: Dispatcher<'a, bool>,
+    label: StaticLabel,
+    style: ToggleStyle,
+    // The height of the track, it is twice as wide
+    size: f32,
+    // Where it asks to be placed when it is laid out
+    position: LayoutPoint,
+    indicator: LayoutRect,
+    value: bool,
+    interaction: Interaction
+}
+
+impl<'a> Toggle<'a> {
+    pub fn new(on: Selector<'a, bool>, on_change: Dispatcher<'a, bool>, content: &str, font: Font, position: LayoutPoint) -> Self {
+        Toggle::with_style(on, on_change, content, font, ToggleStyle::default(), position)
+    }
+
+    pub fn with_style(on: Selector<'a, bool>, on_change: Dispatcher<'a, bool>, content: &str, font: Font, style: ToggleStyle, position: LayoutPoint) -> Self {
+        let size = font.size;
+        Toggle {
+            on,
+            on_change,
+            label: indicator_label(content, font, position, style.text),
+            style,
+            size,
+            position,
+            indicator: LayoutRect::new(position, LayoutSize::new(size * 2.0, size)),
+            value: false,
+            interaction: Interaction::default()
+        }
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for Toggle<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let mut info = CommonItemProperties::new(self.indicator, render_data.space_clip());
+        info.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_hit_test(&info);
+
+        let radius = self.size / 2.0;
+        let track = if self.value { self.style.accent } else { self.style.background };
+        push_indicator_box(ctx, render_data, self.indicator, radius, self.style.border(&self.interaction), track);
+
+        let knob_size = self.size - 4.0;
+        let knob_x = if self.value { self.indicator.max_x() - 2.0 - knob_size } else { self.indicator.min_x() + 2.0 };
+        let knob = LayoutRect::new(LayoutPoint::new(knob_x, self.indicator.min_y() + 2.0), LayoutSize::new(knob_size, knob_size));
+        let knob_color = if self.value { self.style.background } else { self.style.border };
+        push_rounded_rect(ctx, render_data, knob, knob_size / 2.0, knob_color);
+        self.label.draw(ctx, render_data);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        self.value = (self.on)();
+        self.label.update(ctx);
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        match self.interaction.handle(&event, true) {
+            Some(true) => (self.on_change)(!self.value),
+            Some(false) => (),
+            None => return false
+        }
+        true
+    }
+}
+
+impl<'a> Layout for Toggle<'a> {
+    fn measure(&self) -> LayoutSize {
+        measure_row(LayoutSize::new(self.size * 2.0, self.size), &self.label)
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.indicator = arrange_row(bounds, LayoutSize::new(self.size * 2.0, self.size), &mut self.label);
+    }
+}
+
+struct RadioOption<T> {
+    value: T,
+    label: StaticLabel,
+    // The whole row, which can be clicked
+    row: LayoutRect,
+    indicator: LayoutRect
+}
+
+// A column of options of which the one equal to the selector's value is selected.
+// Clicking an option dispatches its value, while the group is focused the arrow keys move
+// between the options and space or enter selects the highlighted one.
+pub struct RadioGroup<'a, T> where T: Clone + PartialEq {
+    selected: Selector<'a, T>,
+    on_select: Dispatcher<'a, T>,
+    options: Vec<RadioOption<T>>,
+    style: ToggleStyle,
+    // The size of the circles
+    size: f32,
+    // Where it asks to be placed when it is laid out
+    position: LayoutPoint,
+    value: Option<usize>,
+    // The option under the pointer when it was pressed
+    pressed: Option<usize>,
+    // The option the arrow keys moved to
+    highlighted: Option<usize>,
+    interaction: Interaction
+}
+
+impl<'a, T> RadioGroup<'a, T> where T: Clone + PartialEq {
+    pub fn new(selected: Selector<'a, T>, on_select: Dispatcher<'a, T>, options: Vec<(T, &str)>, font: Font, position: LayoutPoint) -> Self {
+        RadioGroup::with_style(selected, on_select, options, font, ToggleStyle::default(), position)
+    }
+
+    pub fn with_style(selected: Selector<'a, T>, on_select: Dispatcher<'a, T>, options: Vec<(T, &str)>, font: Font, style: ToggleStyle, position: LayoutPoint) -> Self {
+        let size = font.size;
+        let options = options.into_iter()
+            .map(|(value, content)| RadioOption {
+                value,
+                label: indicator_label(content, font.clone(), position, style.text),
+                row: LayoutRect::new(position, LayoutSize::zero()),
+                indicator: LayoutRect::new(position, LayoutSize::new(size, size))
+            })
+            .collect();
+        RadioGroup {
+            selected,
+            on_select,
+            options,
+            style,
+            size,
+            position,
+            value: None,
+            pressed: None,
+            highlighted: None,
+            interaction: Interaction::default()
+        }
+    }
+
+    fn option_at(&self, position: LayoutPoint) -> Option<usize> {
+        self.options.iter().position(|o| o.row.contains(position))
+    }
+
+    fn select(&self, index: Option<usize>) {
+        if let Some(option) = index.and_then(|i| self.options.get(i)) {
+            (self.on_select)(option.value.clone());
+        }
+    }
+
+    fn move_highlight(&mut self, step: isize) {
+        if self.options.is_empty() {
+            return
+        }
+        let current = self.highlighted.or(self.value).map_or(-1, |i| i as isize);
+        let last = self.options.len() as isize - 1;
+        self.highlighted = Some(isize::min(isize::max(current + step, 0), last) as usize);
+    }
+}
+
+impl<'a, 'b, T> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for RadioGroup<'a, T> where T: Clone + PartialEq {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let tag = ctx.hit_tag();
+        for (i, option) in self.options.iter().enumerate() {
+            let mut info = CommonItemProperties::new(option.row, render_data.space_clip());
+            info.hit_info = Some(tag);
+            ctx.builder.push_hit_test(&info);
+
+            let highlighted = self.interaction.focused && self.highlighted == Some(i);
+            let border = if highlighted { self.style.hover_border } else { self.style.border };
+            push_indicator_box(ctx, render_data, option.indicator, self.size / 2.0, border, self.style.background);
+            if self.value == Some(i) {
+                let dot = inset(option.indicator, LayoutSideOffsets::new_all_same(self.size / 4.0));
+                push_rounded_rect(ctx, render_data, dot, dot.size.width / 2.0, self.style.accent);
+            }
+            option.label.draw(ctx, render_data);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        let selected = (self.selected)();
+        self.value = self.options.iter().position(|o| o.value == selected);
+        for option in &mut self.options {
+            option.label.update(ctx);
+        }
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        match event {
+            WebrenderEvent::PointerPressed { position, button: MouseButton::Left } => {
+                self.pressed = self.option_at(position);
+                self.highlighted = self.pressed;
+                self.interaction.hovered = true;
+            }
+            WebrenderEvent::PointerMoved { position } if self.interaction.pressed => {
+                // Releasing over another option than the pressed one does not select anything
+                self.interaction.hovered = self.pressed.is_some() && self.option_at(position) == self.pressed;
+                return true
+            }
+            WebrenderEvent::KeyDown { key: VirtualKeyCode::Up, .. } if self.interaction.focused => {
+                self.move_highlight(-1);
+                return true
+            }
+            WebrenderEvent::KeyDown { key: VirtualKeyCode::Down, .. } if self.interaction.focused => {
+                self.move_highlight(1);
+                return true
+            }
+            _ => ()
+        }
+
+        let keyboard = matches!(event, WebrenderEvent::KeyDown { .. });
+        match self.interaction.handle(&event, true) {
+            Some(true) if keyboard => self.select(self.highlighted.or(self.value)),
+            Some(true) => self.select(self.pressed),
+            Some(false) => (),
+            None => return false
+        }
+        true
+    }
+}
+
+impl<'a, T> Layout for RadioGroup<'a, T> where T: Clone + PartialEq {
+    fn measure(&self) -> LayoutSize {
+        let indicator = LayoutSize::new(self.size, self.size);
+        let rows = self.options.iter().map(|o| measure_row(indicator, &o.label));
+        let (width, height) = rows.fold((0.0, 0.0), |(width, height), row| (f32::max(width, row.width), height + row.height));
+        let gaps = self.options.len().saturating_sub(1) as f32 * RADIO_ROW_GAP;
+        LayoutSize::new(width, height + gaps)
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        let indicator = LayoutSize::new(self.size, self.size);
+        let mut y = bounds.origin.y;
+        for option in &mut self.options {
+            let height = measure_row(indicator, &option.label).height;
+            option.row = LayoutRect::new(LayoutPoint::new(bounds.origin.x, y), LayoutSize::new(bounds.size.width, height));
+            option.indicator = arrange_row(option.row, indicator, &mut option.label);
+            y += height + RADIO_ROW_GAP;
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use webrender::RendererError;
+use webrender::api::units::DeviceIntSize;
+
+#[derive(Debug)]
+pub enum Error {
+    MissingFile(PathBuf, io::Error),
+    UnsupportedImage(image::ImageError),
+    InvalidFont(String),
+    RendererInit(RendererError),
+    Render(Vec<RendererError>),
+    // A widget was drawn before its update method was called at least once
+    NotUpdated(&'static str),
+    // An offscreen target has to be the size of the UI, the frame is laid out for that size
+    TargetSizeMismatch { target: DeviceIntSize, ui: DeviceIntSize }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingFile(path, err) => write!(f, "Error opening {}: {}", path.display(), err),
+            Error::UnsupportedImage(err) => write!(f, "Error decoding image: {}", err),
+            Error::InvalidFont(name) => write!(f, "{} is not a TrueType or OpenType font", name),
+            Error::RendererInit(err) => write!(f, "Error creating renderer: {:?}", err),
+            Error::Render(errs) => write!(f, "Error rendering: {:?}", errs),
+            Error::NotUpdated(widget) => write!(f, "{} was drawn before it was updated", widget),
+            Error::TargetSizeMismatch { target, ui } => write!(f, "Cannot render a {}x{} UI into a {}x{} target", ui.width, ui.height, target.width, target.height)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::MissingFile(_, err) => Some(err),
+            Error::UnsupportedImage(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::UnsupportedImage(err)
+    }
+}
@@ -0,0 +1,79 @@
+use glutin::event::{WindowEvent, DeviceEvent, ElementState, KeyboardInput, MouseScrollDelta, ModifiersState};
+use webrender::api::units::{LayoutPoint, LayoutSize, LayoutVector2D};
+use crate::widget::WebrenderEvent;
+
+const LINE_SCROLL_DISTANCE: f32 = 20.0;
+
+// Translates glutin events into WebrenderEvents.
+// Keeps track of the cursor position and modifier keys, since glutin only reports them on some events.
+// Positions and sizes are converted from physical pixels to layout pixels using the window's scale factor.
+pub struct EventTranslator {
+    cursor: LayoutPoint,
+    modifiers: ModifiersState,
+    scale_factor: f32
+}
+
+impl EventTranslator {
+    pub fn new(scale_factor: f32) -> Self {
+        EventTranslator {
+            cursor: LayoutPoint::zero(),
+            modifiers: ModifiersState::default(),
+            scale_factor
+        }
+    }
+
+    pub fn cursor(&self) -> LayoutPoint {
+        self.cursor
+    }
+
+    pub fn translate_window_event(&mut self, event: &WindowEvent) -> Option<WebrenderEvent> {
+        match event {
+            WindowEvent::Resized(size) => {
+                let size = LayoutSize::new(size.width as f32, size.height as f32) / self.scale_factor;
+                Some(WebrenderEvent::Resized(size))
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                self.scale_factor = *scale_factor as f32;
+                let size = LayoutSize::new(new_inner_size.width as f32, new_inner_size.height as f32) / self.scale_factor;
+                Some(WebrenderEvent::Resized(size))
+            }
+            WindowEvent::Focused(focused) => Some(WebrenderEvent::Focused(*focused)),
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                None
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = LayoutPoint::new(position.x as f32, position.y as f32) / self.scale_factor;
+                Some(WebrenderEvent::PointerMoved { position: self.cursor })
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
+                Some(WebrenderEvent::PointerPressed { position: self.cursor, button: *button })
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
+                Some(WebrenderEvent::PointerReleased { position: self.cursor, button: *button })
+            }
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), .. }, .. } => {
+                match state {
+                    ElementState::Pressed => Some(WebrenderEvent::KeyDown { key: *key, modifiers: self.modifiers }),
+                    ElementState::Released => Some(WebrenderEvent::KeyUp { key: *key, modifiers: self.modifiers })
+                }
+            }
+            // Control characters (backspace, delete, enter...) are delivered as key events instead
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => Some(WebrenderEvent::TextInput(*c)),
+            _ => None
+        }
+    }
+
+    pub fn translate_device_event(&mut self, event: &DeviceEvent) -> Option<WebrenderEvent> {
+        match event {
+            DeviceEvent::MouseWheel { delta } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => LayoutVector2D::new(x * LINE_SCROLL_DISTANCE, y * LINE_SCROLL_DISTANCE),
+                    MouseScrollDelta::PixelDelta(pos) => LayoutVector2D::new(pos.x as f32, pos.y as f32)
+                };
+                Some(WebrenderEvent::Wheel { position: self.cursor, delta })
+            }
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use gleam::gl::GLuint;
+use webrender::api::{ExternalImageHandler, ExternalImageId, ExternalImage, ExternalImageSource, ImageRendering, TexelRect};
+use webrender::api::units::DeviceIntSize;
+
+// A GL texture rendered by the host application, e.g. a luminance scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExternalTexture {
+    pub texture: GLuint,
+    pub size: DeviceIntSize
+}
+
+pub type ExternalTextures = Rc<RefCell<HashMap<ExternalImageId, ExternalTexture>>>;
+
+// Hands the registered textures to webrender when it composites external images
+pub struct ExternalTextureHandler {
+    textures: ExternalTextures
+}
+
+impl ExternalTextureHandler {
+    pub fn new(textures: ExternalTextures) -> Self {
+        ExternalTextureHandler {
+            textures
+        }
+    }
+}
+
+impl ExternalImageHandler for ExternalTextureHandler {
+    fn lock(&mut self, key: ExternalImageId, _channel_index: u8, _rendering: ImageRendering) -> ExternalImage<'_> {
+        match self.textures.borrow().get(&key) {
+            Some(texture) => {
+                let width = texture.size.width as f32;
+                let height = texture.size.height as f32;
+                ExternalImage {
+                    // GL textures are stored bottom-up, webrender samples them top-down
+                    uv: TexelRect::new(0.0, height, width, 0.0),
+                    source: ExternalImageSource::NativeTexture(texture.texture)
+                }
+            }
+            None => ExternalImage {
+                uv: TexelRect::new(0.0, 0.0, 0.0, 0.0),
+                source: ExternalImageSource::Invalid
+            }
+        }
+    }
+
+    fn unlock(&mut self, _key: ExternalImageId, _channel_index: u8) {
+    }
+}
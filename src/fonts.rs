@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use webrender::api::{DocumentId, FontInstanceFlags, FontInstanceKey, FontInstanceOptions, FontKey, RenderApi, SyntheticItalics, Transaction};
+use webrender::api::units::Au;
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    pub const NORMAL: FontWeight = FontWeight(400);
+    pub const BOLD: FontWeight = FontWeight(700);
+}
+
+impl Default for FontWeight {
+    fn default() -> Self {
+        FontWeight::NORMAL
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    Normal,
+    Italic
+}
+
+// Written out instead of derived, #[default] on enum variants only exists since Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for FontStyle {
+    fn default() -> Self {
+        FontStyle::Normal
+    }
+}
+
+// The font a widget wants to draw its text with, the size is given in layout pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub family: String,
+    pub size: f32,
+    pub weight: FontWeight,
+    pub style: FontStyle
+}
+
+impl Font {
+    pub fn new(family: &str, size: f32) -> Self {
+        Font {
+            family: family.to_string(),
+            size,
+            weight: FontWeight::default(),
+            style: FontStyle::default()
+        }
+    }
+}
+
+// Vertical metrics of a font in layout pixels, ascent and descent are both positive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32
+}
+
+impl FontMetrics {
+    // Read from the hhea table of the font, returns None if the data is not a TrueType or OpenType font
+    fn parse(data: &[u8]) -> Option<FontMetrics> {
+        let face = ttf_parser::Face::from_slice(data, 0).ok()?;
+        let units_per_em = f32::from(face.units_per_em()?);
+        Some(FontMetrics {
+            ascent: f32::from(face.ascender()) / units_per_em,
+            descent: -f32::from(face.descender()) / units_per_em,
+            line_gap: f32::from(face.line_gap()) / units_per_em
+        })
+    }
+
+    fn scale(self, size: f32) -> FontMetrics {
+        FontMetrics {
+            ascent: self.ascent * size,
+            descent: self.descent * size,
+            line_gap: self.line_gap * size
+        }
+    }
+
+    // The distance between the baselines of two lines
+    pub fn line_height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+}
+
+// A font instance together with the face it was created from
+#[derive(Clone, Debug)]
+pub struct FontInstance {
+    pub font_key: FontKey,
+    pub inst_key: FontInstanceKey,
+    pub size: f32,
+    pub metrics: FontMetrics,
+    // The font file, for shaping
+    pub data: Rc<Vec<u8>>
+}
+
+struct Face {
+    key: FontKey,
+    data: Rc<Vec<u8>>,
+    // Relative to the font size
+    metrics: FontMetrics,
+    weight: FontWeight,
+    style: FontStyle
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct InstanceKey {
+    family: String,
+    size: Au,
+    weight: FontWeight,
+    style: FontStyle
+}
+
+// Keeps track of the loaded font faces by family and creates font instances for them on demand.
+// Resources are sent to webrender right away, so glyphs can be measured during the same update.
+pub struct FontRegistry {
+    document_id: DocumentId,
+    families: HashMap<String, Vec<Face>>,
+    default_family: Option<String>,
+    fallbacks: Vec<String>,
+    instances: HashMap<InstanceKey, FontInstance>
+}
+
+impl FontRegistry {
+    pub fn new(document_id: DocumentId) -> Self {
+        FontRegistry {
+            document_id,
+            families: HashMap::new(),
+            default_family: None,
+            fallbacks: Vec::new(),
+            instances: HashMap::new()
+        }
+    }
+
+    pub fn add_font_file(&mut self, api: &RenderApi, family: &str, weight: FontWeight, style: FontStyle, path: &Path) -> Result<FontKey> {
+        let bytes = std::fs::read(path).map_err(|e| Error::MissingFile(path.to_path_buf(), e))?;
+        self.add_face(api, family, weight, style, bytes).ok_or_else(|| Error::InvalidFont(path.display().to_string()))
+    }
+
+    pub fn add_raw_font(&mut self, api: &RenderApi, family: &str, weight: FontWeight, style: FontStyle, bytes: Vec<u8>) -> Result<FontKey> {
+        self.add_face(api, family, weight, style, bytes).ok_or_else(|| Error::InvalidFont(family.to_string()))
+    }
+
+    // Webrender only finds out that a font is broken when it rasterizes glyphs, so the metrics are read up front
+    fn add_face(&mut self, api: &RenderApi, family: &str, weight: FontWeight, style: FontStyle, bytes: Vec<u8>) -> Option<FontKey> {
+        let metrics = FontMetrics::parse(&bytes)?;
+        let key = api.generate_font_key();
+        let mut txn = Transaction::new();
+        txn.add_raw_font(key, bytes.clone(), 0);
+
+        // Instances of this family may have picked a worse matching face before, text that uses them is laid out again (see is_current)
+        let outdated: Vec<InstanceKey> = self.instances.keys().filter(|k| k.family == family).cloned().collect();
+        for outdated in outdated {
+            if let Some(instance) = self.instances.remove(&outdated) {
+                txn.delete_font_instance(instance.inst_key);
+            }
+        }
+        api.send_transaction(self.document_id, txn);
+
+        self.families.entry(family.to_string()).or_default().push(Face {
+            key,
+            data: Rc::new(bytes),
+            metrics,
+            weight,
+            style
+        });
+        if self.default_family.is_none() {
+            self.default_family = Some(family.to_string());
+        }
+        Some(key)
+    }
+
+    // Whether the instance still belongs to the registry, instances are deleted when a face is added to their family
+    pub fn is_current(&self, instance: &FontInstance) -> bool {
+        self.instances.values().any(|i| i.inst_key == instance.inst_key)
+    }
+
+    // Families that are searched in order for characters the requested family has no glyphs for
+    pub fn set_fallbacks(&mut self, families: Vec<String>) {
+        self.fallbacks = families;
+    }
+
+    // Returns the instance for the font, creating it if it does not exist yet.
+    // Unknown families fall back to the first family that was loaded, missing weights and styles are synthesized.
+    pub fn instance(&mut self, api: &RenderApi, font: &Font) -> Option<FontInstance> {
+        let family = if self.families.contains_key(&font.family) {
+            font.family.clone()
+        } else {
+            self.default_family.clone()?
+        };
+        self.family_instance(api, family, font)
+    }
+
+    // Returns the instance for the font followed by the instances of the fallback families at the same size
+    pub fn fallback_chain(&mut self, api: &RenderApi, font: &Font) -> Vec<FontInstance> {
+        let mut chain: Vec<FontInstance> = self.instance(api, font).into_iter().collect();
+        for family in self.fallbacks.clone() {
+            if !self.families.contains_key(&family) {
+                continue
+            }
+
+            if let Some(instance) = self.family_instance(api, family, font) {
+                if chain.iter().all(|i| i.font_key != instance.font_key) {
+                    chain.push(instance);
+                }
+            }
+        }
+        chain
+    }
+
+    fn family_instance(&mut self, api: &RenderApi, family: String, font: &Font) -> Option<FontInstance> {
+        let key = InstanceKey {
+            family,
+            size: Au::from_f32_px(font.size),
+            weight: font.weight,
+            style: font.style
+        };
+        if let Some(instance) = self.instances.get(&key) {
+            return Some(instance.clone())
+        }
+
+        let face = self.families[&key.family]
+            .iter()
+            .min_by_key(|face| {
+                let style_penalty = if face.style == font.style { 0 } else { 1000 };
+                style_penalty + (i32::from(face.weight.0) - i32::from(font.weight.0)).abs()
+            })?;
+
+        let mut options = FontInstanceOptions::default();
+        if font.weight.0 >= 600 && face.weight.0 < 600 {
+            options.flags |= FontInstanceFlags::SYNTHETIC_BOLD;
+        }
+        if font.style == FontStyle::Italic && face.style != FontStyle::Italic {
+            options.synthetic_italics = SyntheticItalics::enabled();
+        }
+
+        let inst_key = api.generate_font_instance_key();
+        let mut txn = Transaction::new();
+        txn.add_font_instance(inst_key, face.key, key.size, Some(options), None, vec![]);
+        api.send_transaction(self.document_id, txn);
+
+        let instance = FontInstance {
+            font_key: face.key,
+            inst_key,
+            size: font.size,
+            metrics: face.metrics.scale(font.size),
+            data: face.data.clone()
+        };
+        self.instances.insert(key, instance.clone());
+        Some(instance)
+    }
+}
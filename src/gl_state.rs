@@ -0,0 +1,196 @@
+use gleam::gl::{self, Gl, GLenum, GLint, GLuint};
+
+const CAPABILITIES: [GLenum; 5] = [
+    gl::BLEND,
+    gl::DEPTH_TEST,
+    gl::SCISSOR_TEST,
+    gl::CULL_FACE,
+    gl::STENCIL_TEST
+];
+
+// Capabilities that only exist on desktop GL, querying them is an error on GLES
+const GL_ONLY_CAPABILITIES: [GLenum; 1] = [
+    gl::FRAMEBUFFER_SRGB
+];
+
+// Texture units beyond this are not touched by webrender
+const MAX_SAVED_TEXTURE_UNITS: GLint = 16;
+
+fn get_integer(gl: &dyn Gl, name: GLenum) -> GLint {
+    let mut result = [0];
+    unsafe {
+        gl.get_integer_v(name, &mut result);
+    }
+    result[0]
+}
+
+fn get_integers(gl: &dyn Gl, name: GLenum) -> [GLint; 4] {
+    let mut result = [0; 4];
+    unsafe {
+        gl.get_integer_v(name, &mut result);
+    }
+    result
+}
+
+fn set_enabled(gl: &dyn Gl, cap: GLenum, enabled: bool) {
+    if enabled {
+        gl.enable(cap);
+    } else {
+        gl.disable(cap);
+    }
+}
+
+struct TextureUnit {
+    texture_2d: GLint,
+    texture_2d_array: GLint,
+    texture_rectangle: Option<GLint>
+}
+
+// A snapshot of the GL state that webrender and luminance both modify.
+// Both renderers cache parts of this state, so it has to be exactly the same after the other one is done drawing.
+pub struct GlState {
+    draw_framebuffer: GLint,
+    read_framebuffer: GLint,
+    viewport: [GLint; 4],
+    scissor_box: [GLint; 4],
+    capabilities: Vec<(GLenum, bool)>,
+    blend_func: [GLint; 4],
+    blend_equation: [GLint; 2],
+    depth_func: GLint,
+    depth_mask: bool,
+    color_mask: [bool; 4],
+    program: GLint,
+    vertex_array: GLint,
+    array_buffer: GLint,
+    pixel_pack_buffer: GLint,
+    pixel_unpack_buffer: GLint,
+    pack_alignment: GLint,
+    unpack_alignment: GLint,
+    unpack_row_length: GLint,
+    active_texture: GLint,
+    texture_units: Vec<TextureUnit>
+}
+
+impl GlState {
+    pub fn save(gl: &dyn Gl) -> Self {
+        let gl_only: &[GLenum] = match gl.get_type() {
+            gl::GlType::Gl => &GL_ONLY_CAPABILITIES,
+            gl::GlType::Gles => &[]
+        };
+        let capabilities = CAPABILITIES
+            .iter()
+            .chain(gl_only)
+            .map(|&cap| (cap, gl.is_enabled(cap) != 0))
+            .collect();
+
+        let mut depth_mask = [0];
+        let mut color_mask = [0; 4];
+        unsafe {
+            gl.get_boolean_v(gl::DEPTH_WRITEMASK, &mut depth_mask);
+            gl.get_boolean_v(gl::COLOR_WRITEMASK, &mut color_mask);
+        }
+
+        let active_texture = get_integer(gl, gl::ACTIVE_TEXTURE);
+        let unit_count = GLint::min(get_integer(gl, gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS), MAX_SAVED_TEXTURE_UNITS);
+        let texture_units = (0..unit_count).map(|unit| {
+            gl.active_texture(gl::TEXTURE0 + unit as GLenum);
+            TextureUnit {
+                texture_2d: get_integer(gl, gl::TEXTURE_BINDING_2D),
+                texture_2d_array: get_integer(gl, gl::TEXTURE_BINDING_2D_ARRAY),
+                // Rectangle textures do not exist on GLES
+                texture_rectangle: match gl.get_type() {
+                    gl::GlType::Gl => Some(get_integer(gl, gl::TEXTURE_BINDING_RECTANGLE)),
+                    gl::GlType::Gles => None
+                }
+            }
+        }).collect();
+        gl.active_texture(active_texture as GLenum);
+
+        let blend_func = [
+            get_integer(gl, gl::BLEND_SRC_RGB),
+            get_integer(gl, gl::BLEND_DST_RGB),
+            get_integer(gl, gl::BLEND_SRC_ALPHA),
+            get_integer(gl, gl::BLEND_DST_ALPHA)
+        ];
+
+        GlState {
+            draw_framebuffer: get_integer(gl, gl::DRAW_FRAMEBUFFER_BINDING),
+            read_framebuffer: get_integer(gl, gl::READ_FRAMEBUFFER_BINDING),
+            viewport: get_integers(gl, gl::VIEWPORT),
+            scissor_box: get_integers(gl, gl::SCISSOR_BOX),
+            capabilities,
+            blend_func,
+            blend_equation: [get_integer(gl, gl::BLEND_EQUATION_RGB), get_integer(gl, gl::BLEND_EQUATION_ALPHA)],
+            depth_func: get_integer(gl, gl::DEPTH_FUNC),
+            depth_mask: depth_mask[0] != 0,
+            color_mask: [color_mask[0] != 0, color_mask[1] != 0, color_mask[2] != 0, color_mask[3] != 0],
+            program: get_integer(gl, gl::CURRENT_PROGRAM),
+            vertex_array: get_integer(gl, gl::VERTEX_ARRAY_BINDING),
+            array_buffer: get_integer(gl, gl::ARRAY_BUFFER_BINDING),
+            pixel_pack_buffer: get_integer(gl, gl::PIXEL_PACK_BUFFER_BINDING),
+            pixel_unpack_buffer: get_integer(gl, gl::PIXEL_UNPACK_BUFFER_BINDING),
+            pack_alignment: get_integer(gl, gl::PACK_ALIGNMENT),
+            unpack_alignment: get_integer(gl, gl::UNPACK_ALIGNMENT),
+            unpack_row_length: get_integer(gl, gl::UNPACK_ROW_LENGTH),
+            active_texture,
+            texture_units
+        }
+    }
+
+    pub fn restore(&self, gl: &dyn Gl) {
+        gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, self.draw_framebuffer as GLuint);
+        gl.bind_framebuffer(gl::READ_FRAMEBUFFER, self.read_framebuffer as GLuint);
+        gl.viewport(self.viewport[0], self.viewport[1], self.viewport[2], self.viewport[3]);
+        gl.scissor(self.scissor_box[0], self.scissor_box[1], self.scissor_box[2], self.scissor_box[3]);
+
+        for &(cap, enabled) in &self.capabilities {
+            set_enabled(gl, cap, enabled);
+        }
+
+        gl.blend_func_separate(self.blend_func[0] as GLenum, self.blend_func[1] as GLenum, self.blend_func[2] as GLenum, self.blend_func[3] as GLenum);
+        gl.blend_equation_separate(self.blend_equation[0] as GLenum, self.blend_equation[1] as GLenum);
+        gl.depth_func(self.depth_func as GLenum);
+        gl.depth_mask(self.depth_mask);
+        gl.color_mask(self.color_mask[0], self.color_mask[1], self.color_mask[2], self.color_mask[3]);
+
+        gl.use_program(self.program as GLuint);
+        gl.bind_vertex_array(self.vertex_array as GLuint);
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.array_buffer as GLuint);
+        gl.bind_buffer(gl::PIXEL_PACK_BUFFER, self.pixel_pack_buffer as GLuint);
+        gl.bind_buffer(gl::PIXEL_UNPACK_BUFFER, self.pixel_unpack_buffer as GLuint);
+        gl.pixel_store_i(gl::PACK_ALIGNMENT, self.pack_alignment);
+        gl.pixel_store_i(gl::UNPACK_ALIGNMENT, self.unpack_alignment);
+        gl.pixel_store_i(gl::UNPACK_ROW_LENGTH, self.unpack_row_length);
+
+        for (unit, textures) in self.texture_units.iter().enumerate() {
+            gl.active_texture(gl::TEXTURE0 + unit as GLenum);
+            gl.bind_texture(gl::TEXTURE_2D, textures.texture_2d as GLuint);
+            gl.bind_texture(gl::TEXTURE_2D_ARRAY, textures.texture_2d_array as GLuint);
+            if let Some(texture_rectangle) = textures.texture_rectangle {
+                gl.bind_texture(gl::TEXTURE_RECTANGLE, texture_rectangle as GLuint);
+            }
+        }
+        gl.active_texture(self.active_texture as GLenum);
+    }
+}
+
+// Saves the GL state when created and restores it when dropped
+pub struct GlStateGuard<'a> {
+    gl: &'a dyn Gl,
+    state: GlState
+}
+
+impl<'a> GlStateGuard<'a> {
+    pub fn new(gl: &'a dyn Gl) -> Self {
+        GlStateGuard {
+            gl,
+            state: GlState::save(gl)
+        }
+    }
+}
+
+impl<'a> Drop for GlStateGuard<'a> {
+    fn drop(&mut self) {
+        self.state.restore(self.gl);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use webrender::api::{ItemTag, HitTestResult};
+
+// Indices of the children to follow from the root to reach a component
+pub type ComponentPath = Vec<usize>;
+
+// Hands out a unique ItemTag to every component that draws hit-testable items
+// and remembers where in the component tree the tag came from, so hit test results can be routed back.
+pub struct HitTagRegistry {
+    current: ComponentPath,
+    tags: HashMap<ComponentPath, ItemTag>,
+    paths: Vec<ComponentPath>
+}
+
+impl Default for HitTagRegistry {
+    fn default() -> Self {
+        HitTagRegistry::new()
+    }
+}
+
+impl HitTagRegistry {
+    pub fn new() -> Self {
+        HitTagRegistry {
+            current: Vec::new(),
+            tags: HashMap::new(),
+            paths: Vec::new()
+        }
+    }
+
+    // Called by containers before drawing their child at the given index
+    pub fn enter(&mut self, index: usize) {
+        self.current.push(index);
+    }
+
+    // Called by containers after drawing a child
+    pub fn leave(&mut self) {
+        self.current.pop();
+    }
+
+    // Returns the tag of the component currently being drawn, allocating one if necessary
+    pub fn tag(&mut self) -> ItemTag {
+        if let Some(&tag) = self.tags.get(&self.current) {
+            return tag
+        }
+
+        let tag = (self.paths.len() as u64, 0);
+        self.paths.push(self.current.clone());
+        self.tags.insert(self.current.clone(), tag);
+        tag
+    }
+
+    pub fn resolve(&self, tag: ItemTag) -> Option<&[usize]> {
+        self.paths.get(tag.0 as usize).map(|p| p.as_slice())
+    }
+
+    // Finds the path of the topmost component hit
+    pub fn resolve_hit(&self, hit: &HitTestResult) -> Option<&[usize]> {
+        hit.items.iter().filter_map(|item| self.resolve(item.tag)).next()
+    }
+}
@@ -0,0 +1,1055 @@
+use std::ops::Range;
+use webrender::api::*;
+use webrender::api::units::*;
+use glutin::event::{MouseButton, VirtualKeyCode, ModifiersState};
+use crate::component::Component;
+use crate::state::{Selector, Dispatcher};
+use crate::layout::Layout;
+use crate::text::{LayoutedText, GlyphRun, ParagraphStyle, Overflow, VerticalAlign};
+use crate::fonts::Font;
+use crate::error::Error;
+use crate::widget::*;
+
+const CARET_WIDTH: f32 = 1.0;
+const SELECTION_COLOR: ColorF = ColorF { r: 0.2, g: 0.4, b: 1.0, a: 0.4 };
+
+// Access to the system clipboard, the embedding application knows how to reach it
+pub trait Clipboard {
+    fn get(&self) -> Option<String>;
+    fn set(&self, text: String);
+}
+
+// Text being edited together with the caret and selection, offsets are in bytes and always on character boundaries.
+// The selection lies between the anchor and the caret and is empty if they are equal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextBuffer {
+    text: String,
+    caret: usize,
+    anchor: usize
+}
+
+impl TextBuffer {
+    pub fn new(text: String) -> Self {
+        let caret = text.len();
+        TextBuffer {
+            text,
+            caret,
+            anchor: caret
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    // Replaces the text, the caret and selection stay where they were as far as possible
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.caret = self.clamp(self.caret);
+        self.anchor = self.clamp(self.anchor);
+    }
+
+    fn clamp(&self, mut offset: usize) -> usize {
+        offset = usize::min(offset, self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    // Moves the caret, keeping the anchor in place if the selection is extended
+    pub fn set_caret(&mut self, offset: usize, extend: bool) {
+        self.caret = self.clamp(offset);
+        if !extend {
+            self.anchor = self.caret;
+        }
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        usize::min(self.caret, self.anchor)..usize::max(self.caret, self.anchor)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+    }
+
+    // Replaces the selection with the text and places the caret behind it
+    pub fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), text);
+        self.set_caret(selection.start + text.len(), false);
+    }
+
+    // Deletes the selection, or the character or word in front of the caret if nothing is selected
+    pub fn delete_backward(&mut self, word: bool) {
+        if self.caret == self.anchor {
+            let target = if word { self.previous_word(self.caret) } else { self.previous_char(self.caret) };
+            self.anchor = target;
+        }
+        self.insert("");
+    }
+
+    // Deletes the selection, or the character or word behind the caret if nothing is selected
+    pub fn delete_forward(&mut self, word: bool) {
+        if self.caret == self.anchor {
+            let target = if word { self.next_word(self.caret) } else { self.next_char(self.caret) };
+            self.anchor = target;
+        }
+        self.insert("");
+    }
+
+    // Moving without extending collapses the selection to the side the caret moves to
+    pub fn move_left(&mut self, word: bool, extend: bool) {
+        let selection = self.selection();
+        let target = if !extend && !selection.is_empty() && !word {
+            selection.start
+        } else if word {
+            self.previous_word(self.caret)
+        } else {
+            self.previous_char(self.caret)
+        };
+        self.set_caret(target, extend);
+    }
+
+    pub fn move_right(&mut self, word: bool, extend: bool) {
+        let selection = self.selection();
+        let target = if !extend && !selection.is_empty() && !word {
+            selection.end
+        } else if word {
+            self.next_word(self.caret)
+        } else {
+            self.next_char(self.caret)
+        };
+        self.set_caret(target, extend);
+    }
+
+    pub fn home(&mut self, extend: bool) {
+        self.set_caret(0, extend);
+    }
+
+    pub fn end(&mut self, extend: bool) {
+        self.set_caret(self.text.len(), extend);
+    }
+
+    pub fn previous_char(&self, offset: usize) -> usize {
+        self.text[..offset].char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    pub fn next_char(&self, offset: usize) -> usize {
+        self.text[offset..].chars().next().map_or(offset, |c| offset + c.len_utf8())
+    }
+
+    // The start of the word in front of the offset, whitespace in between is skipped
+    pub fn previous_word(&self, offset: usize) -> usize {
+        let before = self.text[..offset].trim_end();
+        before.rfind(char::is_whitespace).map_or(0, |i| self.next_char(i))
+    }
+
+    // The end of the word behind the offset, whitespace in between is skipped
+    pub fn next_word(&self, offset: usize) -> usize {
+        let after = &self.text[offset..];
+        let start = after.len() - after.trim_start().len();
+        after[start..].find(char::is_whitespace).map_or(self.text.len(), |i| offset + start + i)
+    }
+}
+
+// Rectangles are drawn with the size of their clip rect
+fn push_clipped_rect(ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData, rect: LayoutRect, clip: LayoutRect, color: ColorF) {
+    if let Some(rect) = rect.intersection(&clip) {
+        ctx.builder.push_rect(&CommonItemProperties::new(rect, render_data.space_clip()), color);
+    }
+}
+
+// Returns false if there is no clipboard or nothing is selected
+fn copy_selection(clipboard: &Option<Box<dyn Clipboard + '_>>, buffer: &TextBuffer) -> bool {
+    match clipboard {
+        Some(clipboard) if !buffer.selection().is_empty() => {
+            clipboard.set(buffer.selected_text().to_string());
+            true
+        }
+        _ => false
+    }
+}
+
+// Text an input method is composing, shown at the caret until it is committed
+#[derive(Clone, Debug, PartialEq)]
+struct Composition {
+    text: String,
+    // Byte offset into the composed text
+    cursor: Option<usize>
+}
+
+impl Composition {
+    // An empty text ends the composition
+    fn new(text: String, cursor: Option<usize>) -> Option<Self> {
+        if text.is_empty() {
+            None
+        } else {
+            Some(Composition { text, cursor })
+        }
+    }
+
+    fn range(&self, buffer: &TextBuffer) -> Range<usize> {
+        buffer.caret()..buffer.caret() + self.text.len()
+    }
+}
+
+// The text as it is shown, with the composition inserted at the caret
+fn display_text(buffer: &TextBuffer, composition: &Option<Composition>) -> String {
+    let mut text = buffer.text().to_string();
+    if let Some(composition) = composition {
+        text.insert_str(buffer.caret(), &composition.text);
+    }
+    text
+}
+
+// Where the caret is shown, inside the composition while there is one
+fn display_caret(buffer: &TextBuffer, composition: &Option<Composition>) -> usize {
+    match composition {
+        Some(composition) => buffer.caret() + usize::min(composition.cursor.unwrap_or(composition.text.len()), composition.text.len()),
+        None => buffer.caret()
+    }
+}
+
+// The selection, or the underline of the composition while there is one
+fn marks(text: &LayoutedText, text_bounds: LayoutRect, buffer: &TextBuffer, composition: &Option<Composition>, color: ColorF) -> Vec<(LayoutRect, ColorF)> {
+    match composition {
+        Some(composition) => text.selection_rects(text_bounds, composition.range(buffer))
+            .into_iter()
+            .map(|rect| {
+                let underline = LayoutRect::new(LayoutPoint::new(rect.min_x(), rect.min_y() + text.baseline + 1.0), LayoutSize::new(rect.size.width, 1.0));
+                (underline, color)
+            })
+            .collect(),
+        None => text.selection_rects(text_bounds, buffer.selection())
+            .into_iter()
+            .map(|rect| (rect, SELECTION_COLOR))
+            .collect()
+    }
+}
+
+// A single line text field that shows the value of a store and sends every edit back to it.
+// The text scrolls horizontally to keep the caret visible, line breaks are removed from pasted text.
+pub struct TextInput<'a> {
+    value: Selector<'a, String>,
+    on_change: Dispatcher<'a, String>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
+    font: Font,
+    style: ParagraphStyle,
+    buffer: TextBuffer,
+    text: Option<LayoutedText>,
+    glyph_runs: Vec<GlyphRun>,
+    // The position and width it asks for when it is laid out
+    position: LayoutPoint,
+    width: f32,
+    area: LayoutRect,
+    // How far the text is moved to the left
+    scroll: f32,
+    composition: Option<Composition>,
+    focused: bool,
+    dragging: bool,
+    color: ColorF
+}
+
+impl<'a> TextInput<'a> {
+    pub fn new(value: Selector<'a, String>, on_change: Dispatcher<'a, String>, font: Font, position: LayoutPoint, width: f32, color: ColorF) -> Self {
+        let style = ParagraphStyle {
+            overflow: Overflow::Clip,
+            vertical_align: VerticalAlign::Middle,
+            ..ParagraphStyle::default()
+        };
+        TextInput {
+            value,
+            on_change,
+            clipboard: None,
+            font,
+            style,
+            buffer: TextBuffer::default(),
+            text: None,
+            glyph_runs: Vec::new(),
+            position,
+            width,
+            area: LayoutRect::new(position, LayoutSize::new(width, 0.0)),
+            scroll: 0.0,
+            composition: None,
+            focused: false,
+            dragging: false,
+            color
+        }
+    }
+
+    // Without a clipboard, copy, cut and paste do nothing
+    pub fn set_clipboard<C>(&mut self, clipboard: C) where C: Clipboard + 'a {
+        self.clipboard = Some(Box::new(clipboard));
+    }
+
+    fn line_height(&self) -> f32 {
+        self.text.as_ref().map_or(0.0, |t| t.line_height)
+    }
+
+    fn bounds(&self) -> LayoutRect {
+        LayoutRect::new(self.area.origin, LayoutSize::new(self.area.size.width, f32::max(self.area.size.height, self.line_height())))
+    }
+
+    // The box the text is placed in, wide enough for the whole text and moved by the scroll offset
+    fn text_bounds(&self, text: &LayoutedText) -> LayoutRect {
+        let bounds = self.bounds();
+        let width = f32::max(bounds.size.width, text.size.width + CARET_WIDTH);
+        LayoutRect::new(bounds.origin - LayoutVector2D::new(self.scroll, 0.0), LayoutSize::new(width, bounds.size.height))
+    }
+
+    fn relayout(&mut self) {
+        if let Some(text) = &self.text {
+            let max_scroll = f32::max(text.size.width + CARET_WIDTH - self.area.size.width, 0.0);
+            let (_, caret) = text.caret_position(self.text_bounds(text), display_caret(&self.buffer, &self.composition));
+            let caret = caret - self.area.origin.x + self.scroll;
+            if caret < self.scroll {
+                self.scroll = caret;
+            } else if caret + CARET_WIDTH > self.scroll + self.area.size.width {
+                self.scroll = caret + CARET_WIDTH - self.area.size.width;
+            }
+            self.scroll = f32::min(f32::max(self.scroll, 0.0), max_scroll);
+            self.glyph_runs = text.glyph_runs(self.text_bounds(text));
+        }
+    }
+
+    fn changed(&mut self) {
+        (self.on_change)(self.buffer.text().to_string());
+    }
+
+    // Applies an edit to the buffer and reports the new text if the edit changed it
+    fn edit<F>(&mut self, edit: F) where F: FnOnce(&mut TextBuffer) {
+        let before = self.buffer.text().to_string();
+        edit(&mut self.buffer);
+        if self.buffer.text() != before {
+            self.changed();
+        }
+    }
+
+    fn insert(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        self.edit(|buffer| buffer.insert(&text));
+    }
+
+    fn copy(&self) -> bool {
+        copy_selection(&self.clipboard, &self.buffer)
+    }
+
+    fn paste(&mut self) {
+        let pasted = self.clipboard.as_ref().and_then(|c| c.get());
+        if let Some(pasted) = pasted {
+            self.insert(&pasted);
+        }
+    }
+
+    fn offset_at(&self, position: LayoutPoint) -> Option<usize> {
+        self.text.as_ref().map(|text| text.offset_at(self.text_bounds(text), position))
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        let extend = modifiers.shift();
+        let word = modifiers.ctrl() || modifiers.alt();
+        let command = modifiers.ctrl() || modifiers.logo();
+        match key {
+            VirtualKeyCode::Left => self.buffer.move_left(word, extend),
+            VirtualKeyCode::Right => self.buffer.move_right(word, extend),
+            VirtualKeyCode::Home => self.buffer.home(extend),
+            VirtualKeyCode::End => self.buffer.end(extend),
+            VirtualKeyCode::Back => self.edit(|buffer| buffer.delete_backward(word)),
+            VirtualKeyCode::Delete => self.edit(|buffer| buffer.delete_forward(word)),
+            VirtualKeyCode::A if command => self.buffer.select_all(),
+            VirtualKeyCode::C if command => {
+                self.copy();
+            }
+            VirtualKeyCode::X if command => {
+                if self.copy() {
+                    self.insert("");
+                }
+            }
+            VirtualKeyCode::V if command => self.paste(),
+            _ => return false
+        }
+        true
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for TextInput<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let text = match &self.text {
+            Some(text) => text,
+            None => return ctx.report(Error::NotUpdated("TextInput"))
+        };
+        let bounds = self.bounds();
+        let text_bounds = self.text_bounds(text);
+
+        let mut info = CommonItemProperties::new(bounds, render_data.space_clip());
+        info.hit_info = Some(ctx.hit_tag());
+        // An empty field still has to be hit-testable to receive focus
+        ctx.builder.push_hit_test(&info);
+
+        if self.focused {
+            for (rect, color) in marks(text, text_bounds, &self.buffer, &self.composition, self.color) {
+                push_clipped_rect(ctx, render_data, rect, bounds, color);
+            }
+        }
+
+        for run in &self.glyph_runs {
+            ctx.builder.push_text(&info, text_bounds, &run.glyphs, run.inst_key, self.color, Some(GlyphOptions::default()));
+        }
+
+        if self.focused {
+            let mut caret = text.caret_rect(text_bounds, display_caret(&self.buffer, &self.composition));
+            caret.size.width = CARET_WIDTH;
+            push_clipped_rect(ctx, render_data, caret, bounds, self.color);
+            ctx.set_ime_area(caret);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        let value = (self.value)();
+        if value != self.buffer.text() {
+            self.buffer.set_text(value);
+        }
+
+        let display = display_text(&self.buffer, &self.composition);
+        let outdated = match &self.text {
+            Some(text) => text.text != display || ctx.is_outdated(text),
+            None => true
+        };
+        if outdated {
+            self.text = ctx.layout_text(display, &self.font, &self.style);
+        }
+        self.relayout();
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        match event {
+            WebrenderEvent::FocusGained => self.focused = true,
+            WebrenderEvent::FocusLost => {
+                self.focused = false;
+                self.composition = None;
+                self.dragging = false;
+            }
+            WebrenderEvent::PointerPressed { position, button: MouseButton::Left } => {
+                if let Some(offset) = self.offset_at(position) {
+                    self.buffer.set_caret(offset, false);
+                }
+                self.dragging = true;
+            }
+            WebrenderEvent::PointerMoved { position } if self.dragging => {
+                if let Some(offset) = self.offset_at(position) {
+                    self.buffer.set_caret(offset, true);
+                }
+            }
+            WebrenderEvent::PointerReleased { button: MouseButton::Left, .. } => self.dragging = false,
+            WebrenderEvent::KeyDown { key, modifiers } if self.focused => return self.handle_key(key, modifiers),
+            WebrenderEvent::TextInput(c) if self.focused => self.insert(c.encode_utf8(&mut [0; 4])),
+            WebrenderEvent::CompositionUpdate { text, cursor } if self.focused => self.composition = Composition::new(text, cursor),
+            WebrenderEvent::CompositionCommit(text) if self.focused => {
+                self.composition = None;
+                self.insert(&text);
+            }
+            _ => return false
+        }
+        true
+    }
+}
+
+impl<'a> Layout for TextInput<'a> {
+    fn measure(&self) -> LayoutSize {
+        LayoutSize::new(self.width, self.line_height())
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
+        self.relayout();
+    }
+}
+
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EditKind {
+    Typing,
+    Deleting,
+    Other
+}
+
+// Snapshots of the buffer before each edit, consecutive typing or deleting is undone in one step
+#[derive(Default)]
+struct History {
+    undo: Vec<TextBuffer>,
+    redo: Vec<TextBuffer>,
+    last_edit: Option<EditKind>
+}
+
+impl History {
+    fn record(&mut self, buffer: &TextBuffer, kind: EditKind) {
+        if kind == EditKind::Other || self.last_edit != Some(kind) {
+            self.undo.push(buffer.clone());
+            if self.undo.len() > HISTORY_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_edit = Some(kind);
+    }
+
+    // Moving the caret starts a new undo step
+    fn break_coalescing(&mut self) {
+        self.last_edit = None;
+    }
+
+    fn undo(&mut self, buffer: &mut TextBuffer) -> bool {
+        match self.undo.pop() {
+            Some(previous) => {
+                self.redo.push(std::mem::replace(buffer, previous));
+                self.last_edit = None;
+                true
+            }
+            None => false
+        }
+    }
+
+    fn redo(&mut self, buffer: &mut TextBuffer) -> bool {
+        match self.redo.pop() {
+            Some(next) => {
+                self.undo.push(std::mem::replace(buffer, next));
+                self.last_edit = None;
+                true
+            }
+            None => false
+        }
+    }
+
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.last_edit = None;
+    }
+}
+
+// A multi-line text editor that wraps its text to its width and scrolls vertically inside a scroll frame.
+// Like TextInput, it shows the value of a store and sends every edit back to it, changes made by the store clear the undo history.
+pub struct TextArea<'a> {
+    value: Selector<'a, String>,
+    on_change: Dispatcher<'a, String>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
+    font: Font,
+    buffer: TextBuffer,
+    history: History,
+    text: Option<LayoutedText>,
+    glyph_runs: Vec<GlyphRun>,
+    // The position and size it asks for when it is laid out
+    position: LayoutPoint,
+    size: LayoutSize,
+    area: LayoutRect,
+    // How far the text is scrolled down
+    scroll: f32,
+    // The x coordinate the caret tries to stay at when it moves up or down
+    goal_x: Option<f32>,
+    composition: Option<Composition>,
+    // Set when the caret moved, so the next update scrolls it into view
+    follow_caret: bool,
+    focused: bool,
+    dragging: bool,
+    color: ColorF
+}
+
+impl<'a> TextArea<'a> {
+    pub fn new(value: Selector<'a, String>, on_change: Dispatcher<'a, String>, font: Font, position: LayoutPoint, size: LayoutSize, color: ColorF) -> Self {
+        TextArea {
+            value,
+            on_change,
+            clipboard: None,
+            font,
+            buffer: TextBuffer::default(),
+            history: History::default(),
+            text: None,
+            glyph_runs: Vec::new(),
+            position,
+            size,
+            area: LayoutRect::new(position, size),
+            scroll: 0.0,
+            goal_x: None,
+            composition: None,
+            follow_caret: false,
+            focused: false,
+            dragging: false,
+            color
+        }
+    }
+
+    // Without a clipboard, copy, cut and paste do nothing
+    pub fn set_clipboard<C>(&mut self, clipboard: C) where C: Clipboard + 'a {
+        self.clipboard = Some(Box::new(clipboard));
+    }
+
+    fn style(&self) -> ParagraphStyle {
+        ParagraphStyle {
+            max_width: Some(f32::max(self.area.size.width - CARET_WIDTH, 0.0)),
+            ..ParagraphStyle::default()
+        }
+    }
+
+    fn bounds(&self) -> LayoutRect {
+        self.area
+    }
+
+    // The whole text in unscrolled coordinates, the scroll frame moves it into view
+    fn text_bounds(&self, text: &LayoutedText) -> LayoutRect {
+        LayoutRect::new(self.area.origin, LayoutSize::new(self.area.size.width, text.size.height))
+    }
+
+    fn max_scroll(&self) -> f32 {
+        self.text.as_ref().map_or(0.0, |t| f32::max(t.size.height - self.area.size.height, 0.0))
+    }
+
+    // Returns true if the scroll offset changed
+    fn scroll_by(&mut self, delta: f32) -> bool {
+        let scroll = f32::min(f32::max(self.scroll + delta, 0.0), self.max_scroll());
+        let changed = scroll != self.scroll;
+        self.scroll = scroll;
+        changed
+    }
+
+    fn scroll_to_caret(&mut self) {
+        if let Some(text) = &self.text {
+            let caret = text.caret_rect(self.text_bounds(text), display_caret(&self.buffer, &self.composition));
+            let top = caret.min_y() - self.area.origin.y;
+            let bottom = caret.max_y() - self.area.origin.y;
+            if top < self.scroll {
+                self.scroll = top;
+            } else if bottom > self.scroll + self.area.size.height {
+                self.scroll = bottom - self.area.size.height;
+            }
+        }
+        self.scroll_by(0.0);
+    }
+
+    fn relayout(&mut self) {
+        if let Some(text) = &self.text {
+            self.glyph_runs = text.glyph_runs(self.text_bounds(text));
+        }
+    }
+
+    // Pointer positions are in window coordinates, the text is placed in unscrolled coordinates
+    fn offset_at(&self, position: LayoutPoint) -> Option<usize> {
+        let point = position + LayoutVector2D::new(0.0, self.scroll);
+        self.text.as_ref().map(|text| text.offset_at(self.text_bounds(text), point))
+    }
+
+    // The offset on the line the given number of lines below (or above) the caret, closest to the goal x coordinate
+    fn vertical_offset(&mut self, lines: isize) -> Option<usize> {
+        let text = self.text.as_ref()?;
+        let bounds = self.text_bounds(text);
+        let (line, x) = text.caret_position(bounds, self.buffer.caret());
+        let x = self.goal_x.unwrap_or(x);
+        self.goal_x = Some(x);
+        let target = line as isize + lines;
+        if target < 0 {
+            return Some(0)
+        }
+        if target >= text.lines.len() as isize {
+            return Some(text.text.len())
+        }
+        let y = bounds.origin.y + (target as f32 + 0.5) * text.line_height;
+        Some(text.offset_at(bounds, LayoutPoint::new(x, y)))
+    }
+
+    // The start or end of the line the caret is on
+    fn line_edge(&self, end: bool) -> Option<usize> {
+        let text = self.text.as_ref()?;
+        let bounds = self.text_bounds(text);
+        let (line, _) = text.caret_position(bounds, self.buffer.caret());
+        let rtl = text.lines[line].rtl;
+        let x = if end != rtl { bounds.max_x() + 1.0 } else { bounds.min_x() - 1.0 };
+        let y = bounds.origin.y + (line as f32 + 0.5) * text.line_height;
+        Some(text.offset_at(bounds, LayoutPoint::new(x, y)))
+    }
+
+    fn page_lines(&self) -> isize {
+        let line_height = self.text.as_ref().map_or(0.0, |t| t.line_height);
+        if line_height > 0.0 {
+            isize::max((self.area.size.height / line_height) as isize - 1, 1)
+        } else {
+            1
+        }
+    }
+
+    fn changed(&mut self) {
+        (self.on_change)(self.buffer.text().to_string());
+    }
+
+    // Applies an edit to the buffer, it is only recorded and reported if it changed the text
+    fn edit<F>(&mut self, kind: EditKind, edit: F) where F: FnOnce(&mut TextBuffer) {
+        let before = self.buffer.clone();
+        edit(&mut self.buffer);
+        if self.buffer.text() != before.text() {
+            self.history.record(&before, kind);
+            self.changed();
+        }
+    }
+
+    fn insert(&mut self, text: &str, kind: EditKind) {
+        let text: String = text.chars().filter(|&c| c == '\n' || !c.is_control()).collect();
+        self.edit(kind, |buffer| buffer.insert(&text));
+    }
+
+    fn paste(&mut self) {
+        let pasted = self.clipboard.as_ref().and_then(|c| c.get());
+        if let Some(pasted) = pasted {
+            self.insert(&pasted.replace("\r\n", "\n"), EditKind::Other);
+        }
+    }
+
+    fn move_caret(&mut self, offset: Option<usize>, extend: bool) {
+        if let Some(offset) = offset {
+            self.buffer.set_caret(offset, extend);
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        let extend = modifiers.shift();
+        let word = modifiers.ctrl() || modifiers.alt();
+        let command = modifiers.ctrl() || modifiers.logo();
+        let vertical = matches!(key, VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::PageUp | VirtualKeyCode::PageDown);
+        if !vertical {
+            self.goal_x = None;
+        }
+
+        match key {
+            VirtualKeyCode::Left => self.buffer.move_left(word, extend),
+            VirtualKeyCode::Right => self.buffer.move_right(word, extend),
+            VirtualKeyCode::Up => {
+                let offset = self.vertical_offset(-1);
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::Down => {
+                let offset = self.vertical_offset(1);
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::PageUp => {
+                let offset = self.vertical_offset(-self.page_lines());
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::PageDown => {
+                let offset = self.vertical_offset(self.page_lines());
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::Home if command => self.buffer.home(extend),
+            VirtualKeyCode::End if command => self.buffer.end(extend),
+            VirtualKeyCode::Home => {
+                let offset = self.line_edge(false);
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::End => {
+                let offset = self.line_edge(true);
+                self.move_caret(offset, extend);
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.insert("\n", EditKind::Other),
+            VirtualKeyCode::Back => self.edit(EditKind::Deleting, |buffer| buffer.delete_backward(word)),
+            VirtualKeyCode::Delete => self.edit(EditKind::Deleting, |buffer| buffer.delete_forward(word)),
+            VirtualKeyCode::A if command => self.buffer.select_all(),
+            VirtualKeyCode::C if command => {
+                copy_selection(&self.clipboard, &self.buffer);
+            }
+            VirtualKeyCode::X if command => {
+                if copy_selection(&self.clipboard, &self.buffer) {
+                    self.insert("", EditKind::Other);
+                }
+            }
+            VirtualKeyCode::V if command => self.paste(),
+            VirtualKeyCode::Z if command && extend => {
+                if self.history.redo(&mut self.buffer) {
+                    self.changed();
+                }
+            }
+            VirtualKeyCode::Z if command => {
+                if self.history.undo(&mut self.buffer) {
+                    self.changed();
+                }
+            }
+            VirtualKeyCode::Y if command => {
+                if self.history.redo(&mut self.buffer) {
+                    self.changed();
+                }
+            }
+            _ => return false
+        }
+
+        if !matches!(key, VirtualKeyCode::Back | VirtualKeyCode::Delete) {
+            self.history.break_coalescing();
+        }
+        true
+    }
+}
+
+impl<'a, 'b> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for TextArea<'a> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let text = match &self.text {
+            Some(text) => text,
+            None => return ctx.report(Error::NotUpdated("TextArea"))
+        };
+        let bounds = self.bounds();
+        let text_bounds = self.text_bounds(text);
+
+        let mut info = CommonItemProperties::new(bounds, render_data.space_clip());
+        info.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_hit_test(&info);
+
+        let content = LayoutRect::new(self.area.origin, LayoutSize::new(self.area.size.width, f32::max(text.size.height, self.area.size.height)));
+        let frame = ctx.push_scroll_frame(render_data, content, bounds, LayoutVector2D::new(0.0, self.scroll));
+
+        if self.focused {
+            for (rect, color) in marks(text, text_bounds, &self.buffer, &self.composition, self.color) {
+                push_clipped_rect(ctx, &frame, rect, content, color);
+            }
+        }
+
+        let text_info = CommonItemProperties::new(content, frame.space_clip());
+        for run in &self.glyph_runs {
+            ctx.builder.push_text(&text_info, text_bounds, &run.glyphs, run.inst_key, self.color, Some(GlyphOptions::default()));
+        }
+
+        if self.focused {
+            let mut caret = text.caret_rect(text_bounds, display_caret(&self.buffer, &self.composition));
+            caret.size.width = CARET_WIDTH;
+            push_clipped_rect(ctx, &frame, caret, content, self.color);
+            ctx.set_ime_area(caret.translate(LayoutVector2D::new(0.0, -self.scroll)));
+        }
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
+        let value = (self.value)();
+        if value != self.buffer.text() {
+            self.buffer.set_text(value);
+            self.history.clear();
+        }
+
+        let style = self.style();
+        let display = display_text(&self.buffer, &self.composition);
+        let outdated = match &self.text {
+            Some(text) => text.text != display || text.style != style || ctx.is_outdated(text),
+            None => true
+        };
+        if outdated {
+            self.text = ctx.layout_text(display, &self.font, &style);
+        }
+        if self.follow_caret {
+            self.scroll_to_caret();
+            self.follow_caret = false;
+        } else {
+            self.scroll_by(0.0);
+        }
+        self.relayout();
+    }
+
+    fn handle_event(&mut self, event: WebrenderEvent) -> bool {
+        match event {
+            WebrenderEvent::FocusGained => self.focused = true,
+            WebrenderEvent::FocusLost => {
+                self.focused = false;
+                self.composition = None;
+                self.dragging = false;
+            }
+            WebrenderEvent::PointerPressed { position, button: MouseButton::Left } => {
+                let offset = self.offset_at(position);
+                self.move_caret(offset, false);
+                self.goal_x = None;
+                self.history.break_coalescing();
+                self.dragging = true;
+            }
+            WebrenderEvent::PointerMoved { position } if self.dragging => {
+                let offset = self.offset_at(position);
+                self.move_caret(offset, true);
+                self.follow_caret = true;
+            }
+            WebrenderEvent::PointerReleased { button: MouseButton::Left, .. } => self.dragging = false,
+            // A wheel that cannot scroll any further is left to the widgets around the text area
+            WebrenderEvent::Wheel { delta, .. } => return self.scroll_by(-delta.y),
+            WebrenderEvent::KeyDown { key, modifiers } if self.focused => {
+                self.follow_caret = self.handle_key(key, modifiers);
+                return self.follow_caret
+            }
+            WebrenderEvent::TextInput(c) if self.focused => {
+                self.goal_x = None;
+                self.follow_caret = true;
+                self.insert(c.encode_utf8(&mut [0; 4]), EditKind::Typing);
+            }
+            WebrenderEvent::CompositionUpdate { text, cursor } if self.focused => {
+                self.composition = Composition::new(text, cursor);
+                self.follow_caret = true;
+            }
+            WebrenderEvent::CompositionCommit(text) if self.focused => {
+                self.composition = None;
+                self.follow_caret = true;
+                self.insert(&text, EditKind::Other);
+            }
+            _ => return false
+        }
+        true
+    }
+}
+
+impl<'a> Layout for TextArea<'a> {
+    fn measure(&self) -> LayoutSize {
+        self.size
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
+        self.scroll_by(0.0);
+        self.relayout();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str, caret: usize, anchor: usize) -> TextBuffer {
+        let mut buffer = TextBuffer::new(text.to_string());
+        buffer.set_caret(anchor, false);
+        buffer.set_caret(caret, true);
+        buffer
+    }
+
+    #[test]
+    fn new_buffer_has_the_caret_at_the_end() {
+        let buffer = TextBuffer::new("abc".to_string());
+        assert_eq!(buffer.caret(), 3);
+        assert!(buffer.selection().is_empty());
+    }
+
+    #[test]
+    fn char_movement_steps_over_whole_characters() {
+        let mut buffer = buffer("aäb", 0, 0);
+        buffer.move_right(false, false);
+        assert_eq!(buffer.caret(), 1);
+        buffer.move_right(false, false);
+        assert_eq!(buffer.caret(), 3);
+        buffer.move_left(false, false);
+        assert_eq!(buffer.caret(), 1);
+        buffer.move_left(false, false);
+        buffer.move_left(false, false);
+        assert_eq!(buffer.caret(), 0);
+    }
+
+    #[test]
+    fn word_movement_skips_whitespace() {
+        let mut buffer = buffer("one  two three", 0, 0);
+        buffer.move_right(true, false);
+        assert_eq!(buffer.caret(), 3);
+        buffer.move_right(true, false);
+        assert_eq!(buffer.caret(), 8);
+        buffer.move_left(true, false);
+        assert_eq!(buffer.caret(), 5);
+        buffer.move_left(true, false);
+        assert_eq!(buffer.caret(), 0);
+        buffer.end(false);
+        buffer.move_right(true, false);
+        assert_eq!(buffer.caret(), 14);
+    }
+
+    #[test]
+    fn moving_without_extending_collapses_the_selection() {
+        let mut selected = buffer("abcdef", 4, 1);
+        selected.move_left(false, false);
+        assert_eq!((selected.caret(), selected.selection()), (1, 1..1));
+
+        let mut selected = buffer("abcdef", 1, 4);
+        selected.move_right(false, false);
+        assert_eq!((selected.caret(), selected.selection()), (4, 4..4));
+    }
+
+    #[test]
+    fn extending_keeps_the_anchor() {
+        let mut buffer = buffer("abcdef", 2, 2);
+        buffer.move_right(false, true);
+        buffer.move_right(false, true);
+        assert_eq!(buffer.selection(), 2..4);
+        assert_eq!(buffer.selected_text(), "cd");
+        buffer.home(true);
+        assert_eq!(buffer.selection(), 0..2);
+    }
+
+    #[test]
+    fn delete_backward_removes_the_character_word_or_selection() {
+        let mut chars = buffer("abä", 4, 4);
+        chars.delete_backward(false);
+        assert_eq!((chars.text(), chars.caret()), ("ab", 2));
+
+        let mut words = buffer("one two", 7, 7);
+        words.delete_backward(true);
+        assert_eq!((words.text(), words.caret()), ("one ", 4));
+
+        let mut selected = buffer("abcdef", 1, 4);
+        selected.delete_backward(true);
+        assert_eq!((selected.text(), selected.caret()), ("aef", 1));
+
+        let mut start = buffer("abc", 0, 0);
+        start.delete_backward(false);
+        assert_eq!((start.text(), start.caret()), ("abc", 0));
+    }
+
+    #[test]
+    fn delete_forward_removes_the_character_word_or_selection() {
+        let mut chars = buffer("äbc", 0, 0);
+        chars.delete_forward(false);
+        assert_eq!((chars.text(), chars.caret()), ("bc", 0));
+
+        let mut words = buffer("one two", 3, 3);
+        words.delete_forward(true);
+        assert_eq!((words.text(), words.caret()), ("one", 3));
+
+        let mut selected = buffer("abcdef", 4, 1);
+        selected.delete_forward(false);
+        assert_eq!((selected.text(), selected.caret()), ("aef", 1));
+
+        let mut end = buffer("abc", 3, 3);
+        end.delete_forward(false);
+        assert_eq!((end.text(), end.caret()), ("abc", 3));
+    }
+
+    #[test]
+    fn insert_replaces_the_selection() {
+        let mut buffer = buffer("abcdef", 4, 1);
+        buffer.insert("xy");
+        assert_eq!((buffer.text(), buffer.caret()), ("axyef", 3));
+        assert!(buffer.selection().is_empty());
+    }
+
+    #[test]
+    fn offsets_are_clamped_to_character_boundaries() {
+        let mut buffer = buffer("aä", 0, 0);
+        buffer.set_caret(2, false);
+        assert_eq!(buffer.caret(), 1);
+        buffer.set_caret(10, false);
+        assert_eq!(buffer.caret(), 3);
+
+        buffer.set_text("äb".to_string());
+        buffer.set_caret(3, false);
+        buffer.set_text("ä".to_string());
+        assert_eq!(buffer.caret(), 2);
+        buffer.set_caret(1, false);
+        assert_eq!(buffer.caret(), 0);
+    }
+}
@@ -0,0 +1,467 @@
+use webrender::api::units::{LayoutPoint, LayoutRect, LayoutSideOffsets, LayoutSize};
+
+// Components that can be positioned by a container
+pub trait Layout {
+    // The size the component would like to have
+    fn measure(&self) -> LayoutSize;
+    // Positions the component inside the given bounds
+    fn arrange(&mut self, bounds: LayoutRect);
+
+    // Where the component would like to be placed by containers that stack their children, relative to the container
+    fn position(&self) -> LayoutPoint {
+        LayoutPoint::zero()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Direction {
+    Row,
+    Column
+}
+
+// Placement of children on the cross axis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Stretch
+}
+
+// Distribution of free space on the main axis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlexStyle {
+    pub direction: Direction,
+    pub padding: LayoutSideOffsets,
+    pub gap: f32,
+    pub align_items: Align,
+    pub justify_content: Justify
+}
+
+impl Default for FlexStyle {
+    fn default() -> Self {
+        FlexStyle {
+            direction: Direction::Column,
+            padding: LayoutSideOffsets::zero(),
+            gap: 0.0,
+            align_items: Align::Start,
+            justify_content: Justify::Start
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlexItemStyle {
+    pub margin: LayoutSideOffsets,
+    pub grow: f32,
+    pub shrink: f32,
+    pub align_self: Option<Align>
+}
+
+impl Default for FlexItemStyle {
+    fn default() -> Self {
+        FlexItemStyle {
+            margin: LayoutSideOffsets::zero(),
+            grow: 0.0,
+            shrink: 1.0,
+            align_self: None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlexItem {
+    pub size: LayoutSize,
+    pub style: FlexItemStyle
+}
+
+impl FlexItem {
+    pub fn new(size: LayoutSize, style: FlexItemStyle) -> Self {
+        FlexItem {
+            size,
+            style
+        }
+    }
+}
+
+impl Direction {
+    fn main(self, size: LayoutSize) -> f32 {
+        match self {
+            Direction::Row => size.width,
+            Direction::Column => size.height
+        }
+    }
+
+    fn cross(self, size: LayoutSize) -> f32 {
+        match self {
+            Direction::Row => size.height,
+            Direction::Column => size.width
+        }
+    }
+
+    fn main_coord(self, point: LayoutPoint) -> f32 {
+        match self {
+            Direction::Row => point.x,
+            Direction::Column => point.y
+        }
+    }
+
+    fn cross_coord(self, point: LayoutPoint) -> f32 {
+        match self {
+            Direction::Row => point.y,
+            Direction::Column => point.x
+        }
+    }
+
+    // Margin or padding before and after on the main axis
+    fn main_offsets(self, offsets: &LayoutSideOffsets) -> (f32, f32) {
+        match self {
+            Direction::Row => (offsets.left, offsets.right),
+            Direction::Column => (offsets.top, offsets.bottom)
+        }
+    }
+
+    fn cross_offsets(self, offsets: &LayoutSideOffsets) -> (f32, f32) {
+        match self {
+            Direction::Row => (offsets.top, offsets.bottom),
+            Direction::Column => (offsets.left, offsets.right)
+        }
+    }
+
+    fn size(self, main: f32, cross: f32) -> LayoutSize {
+        match self {
+            Direction::Row => LayoutSize::new(main, cross),
+            Direction::Column => LayoutSize::new(cross, main)
+        }
+    }
+
+    fn point(self, main: f32, cross: f32) -> LayoutPoint {
+        match self {
+            Direction::Row => LayoutPoint::new(main, cross),
+            Direction::Column => LayoutPoint::new(cross, main)
+        }
+    }
+}
+
+fn total_gap(style: &FlexStyle, count: usize) -> f32 {
+    if count > 1 {
+        style.gap * (count - 1) as f32
+    } else {
+        0.0
+    }
+}
+
+fn outer_main(dir: Direction, item: &FlexItem) -> f32 {
+    let (before, after) = dir.main_offsets(&item.style.margin);
+    dir.main(item.size) + before + after
+}
+
+fn outer_cross(dir: Direction, item: &FlexItem) -> f32 {
+    let (before, after) = dir.cross_offsets(&item.style.margin);
+    dir.cross(item.size) + before + after
+}
+
+// Computes the size needed to fit all items at their intrinsic size
+pub fn measure(style: &FlexStyle, items: &[FlexItem]) -> LayoutSize {
+    let dir = style.direction;
+    let (pad_main_before, pad_main_after) = dir.main_offsets(&style.padding);
+    let (pad_cross_before, pad_cross_after) = dir.cross_offsets(&style.padding);
+
+    let main = items.iter().map(|i| outer_main(dir, i)).sum::<f32>() + total_gap(style, items.len());
+    let cross = items.iter().map(|i| outer_cross(dir, i)).fold(0.0, f32::max);
+
+    dir.size(main + pad_main_before + pad_main_after, cross + pad_cross_before + pad_cross_after)
+}
+
+// Computes the bounds of every item when laid out inside the given bounds
+pub fn arrange(style: &FlexStyle, bounds: LayoutRect, items: &[FlexItem]) -> Vec<LayoutRect> {
+    let dir = style.direction;
+    let (pad_main_before, pad_main_after) = dir.main_offsets(&style.padding);
+    let (pad_cross_before, pad_cross_after) = dir.cross_offsets(&style.padding);
+    let inner_main = f32::max(0.0, dir.main(bounds.size) - pad_main_before - pad_main_after);
+    let inner_cross = f32::max(0.0, dir.cross(bounds.size) - pad_cross_before - pad_cross_after);
+
+    let mut mains: Vec<f32> = items.iter().map(|i| dir.main(i.size)).collect();
+    let used = items.iter().map(|i| outer_main(dir, i)).sum::<f32>() + total_gap(style, items.len());
+    let mut free = inner_main - used;
+
+    if free > 0.0 {
+        let total_grow: f32 = items.iter().map(|i| i.style.grow).sum();
+        if total_grow > 0.0 {
+            for (main, item) in mains.iter_mut().zip(items) {
+                *main += free * item.style.grow / total_grow;
+            }
+            free = 0.0;
+        }
+    } else if free < 0.0 {
+        // Like CSS, shrinking is weighted by the intrinsic size so small items do not vanish first
+        let total_shrink: f32 = items.iter().map(|i| i.style.shrink * dir.main(i.size)).sum();
+        if total_shrink > 0.0 {
+            for (main, item) in mains.iter_mut().zip(items) {
+                let weight = item.style.shrink * dir.main(item.size) / total_shrink;
+                *main = f32::max(0.0, *main + free * weight);
+            }
+        }
+        free = 0.0;
+    }
+
+    let count = items.len() as f32;
+    let (leading, between) = match style.justify_content {
+        Justify::Start => (0.0, 0.0),
+        Justify::Center => (free / 2.0, 0.0),
+        Justify::End => (free, 0.0),
+        Justify::SpaceBetween if items.len() > 1 => (0.0, free / (count - 1.0)),
+        Justify::SpaceBetween => (0.0, 0.0),
+        Justify::SpaceAround => (free / count / 2.0, free / count),
+        Justify::SpaceEvenly => (free / (count + 1.0), free / (count + 1.0))
+    };
+
+    let main_start = dir.main_coord(bounds.origin) + pad_main_before;
+    let cross_start = dir.cross_coord(bounds.origin) + pad_cross_before;
+
+    let mut cursor = main_start + leading;
+    items.iter().zip(mains).map(|(item, main)| {
+        let (margin_main_before, margin_main_after) = dir.main_offsets(&item.style.margin);
+        let (margin_cross_before, margin_cross_after) = dir.cross_offsets(&item.style.margin);
+        let available_cross = f32::max(0.0, inner_cross - margin_cross_before - margin_cross_after);
+
+        let (cross_offset, cross) = match item.style.align_self.unwrap_or(style.align_items) {
+            Align::Start => (0.0, dir.cross(item.size)),
+            Align::Center => ((available_cross - dir.cross(item.size)) / 2.0, dir.cross(item.size)),
+            Align::End => (available_cross - dir.cross(item.size), dir.cross(item.size)),
+            Align::Stretch => (0.0, available_cross)
+        };
+
+        let main_pos = cursor + margin_main_before;
+        cursor = main_pos + main + margin_main_after + style.gap + between;
+
+        LayoutRect::new(dir.point(main_pos, cross_start + margin_cross_before + cross_offset), dir.size(main, cross))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(width: f32, height: f32) -> FlexItem {
+        FlexItem::new(LayoutSize::new(width, height), FlexItemStyle::default())
+    }
+
+    fn styled(width: f32, height: f32, style: FlexItemStyle) -> FlexItem {
+        FlexItem::new(LayoutSize::new(width, height), style)
+    }
+
+    fn row(justify_content: Justify, align_items: Align) -> FlexStyle {
+        FlexStyle {
+            direction: Direction::Row,
+            align_items,
+            justify_content,
+            ..FlexStyle::default()
+        }
+    }
+
+    fn bounds(width: f32, height: f32) -> LayoutRect {
+        LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(width, height))
+    }
+
+    fn xs(rects: &[LayoutRect]) -> Vec<f32> {
+        rects.iter().map(|r| r.origin.x).collect()
+    }
+
+    fn widths(rects: &[LayoutRect]) -> Vec<f32> {
+        rects.iter().map(|r| r.size.width).collect()
+    }
+
+    #[test]
+    fn measure_adds_padding_margin_and_gap() {
+        let style = FlexStyle {
+            direction: Direction::Row,
+            padding: LayoutSideOffsets::new(1.0, 2.0, 3.0, 4.0),
+            gap: 5.0,
+            ..FlexStyle::default()
+        };
+        let margin = FlexItemStyle {
+            margin: LayoutSideOffsets::new_all_same(10.0),
+            ..FlexItemStyle::default()
+        };
+        let items = [item(10.0, 20.0), styled(30.0, 5.0, margin)];
+        // 10 + 5 + (10 + 30 + 10) + 4 + 2 wide, max(20, 10 + 5 + 10) + 1 + 3 high
+        assert_eq!(measure(&style, &items), LayoutSize::new(71.0, 29.0));
+    }
+
+    #[test]
+    fn measure_column() {
+        let style = FlexStyle {
+            gap: 2.0,
+            ..FlexStyle::default()
+        };
+        assert_eq!(measure(&style, &[item(10.0, 20.0), item(30.0, 5.0)]), LayoutSize::new(30.0, 27.0));
+        assert_eq!(measure(&style, &[]), LayoutSize::zero());
+    }
+
+    #[test]
+    fn grow_distributes_free_space_by_weight() {
+        let grow = |grow| FlexItemStyle {
+            grow,
+            ..FlexItemStyle::default()
+        };
+        let items = [styled(10.0, 10.0, grow(1.0)), styled(10.0, 10.0, grow(3.0)), item(10.0, 10.0)];
+        let rects = arrange(&row(Justify::Start, Align::Start), bounds(110.0, 10.0), &items);
+        assert_eq!(widths(&rects), vec![30.0, 70.0, 10.0]);
+        assert_eq!(xs(&rects), vec![0.0, 30.0, 100.0]);
+    }
+
+    #[test]
+    fn shrink_is_weighted_by_size() {
+        let fixed = FlexItemStyle {
+            shrink: 0.0,
+            ..FlexItemStyle::default()
+        };
+        let items = [item(20.0, 10.0), item(60.0, 10.0), styled(20.0, 10.0, fixed)];
+        let rects = arrange(&row(Justify::Start, Align::Start), bounds(60.0, 10.0), &items);
+        // 40 too wide, split 1:3 between the shrinkable items
+        assert_eq!(widths(&rects), vec![10.0, 30.0, 20.0]);
+        assert_eq!(xs(&rects), vec![0.0, 10.0, 40.0]);
+    }
+
+    #[test]
+    fn shrink_does_not_go_below_zero() {
+        let items = [item(10.0, 10.0), item(10.0, 10.0)];
+        let style = FlexStyle {
+            gap: 30.0,
+            ..row(Justify::Start, Align::Start)
+        };
+        let rects = arrange(&style, bounds(10.0, 10.0), &items);
+        assert!(widths(&rects).iter().all(|&w| w >= 0.0));
+    }
+
+    #[test]
+    fn justify_modes() {
+        let items = [item(10.0, 10.0), item(20.0, 10.0)];
+        let cases = [
+            (Justify::Start, vec![0.0, 10.0]),
+            (Justify::Center, vec![35.0, 45.0]),
+            (Justify::End, vec![70.0, 80.0]),
+            (Justify::SpaceBetween, vec![0.0, 80.0]),
+            (Justify::SpaceAround, vec![17.5, 62.5]),
+            (Justify::SpaceEvenly, vec![70.0 / 3.0, 140.0 / 3.0 + 10.0])
+        ];
+        for (justify, expected) in cases.iter() {
+            let rects = arrange(&row(*justify, Align::Start), bounds(100.0, 10.0), &items);
+            let actual = xs(&rects);
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 0.001, "{:?}: {:?} != {:?}", justify, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn space_between_with_one_item_stays_at_start() {
+        let rects = arrange(&row(Justify::SpaceBetween, Align::Start), bounds(100.0, 10.0), &[item(10.0, 10.0)]);
+        assert_eq!(xs(&rects), vec![0.0]);
+    }
+
+    #[test]
+    fn align_modes() {
+        let items = [item(10.0, 20.0)];
+        let cases = [
+            (Align::Start, 0.0, 20.0),
+            (Align::Center, 40.0, 20.0),
+            (Align::End, 80.0, 20.0),
+            (Align::Stretch, 0.0, 100.0)
+        ];
+        for &(align, y, height) in cases.iter() {
+            let rects = arrange(&row(Justify::Start, align), bounds(100.0, 100.0), &items);
+            assert_eq!(rects[0], LayoutRect::new(LayoutPoint::new(0.0, y), LayoutSize::new(10.0, height)), "{:?}", align);
+        }
+    }
+
+    #[test]
+    fn align_self_overrides_align_items() {
+        let end = FlexItemStyle {
+            align_self: Some(Align::End),
+            ..FlexItemStyle::default()
+        };
+        let rects = arrange(&row(Justify::Start, Align::Start), bounds(100.0, 100.0), &[item(10.0, 20.0), styled(10.0, 20.0, end)]);
+        assert_eq!(rects[0].origin.y, 0.0);
+        assert_eq!(rects[1].origin.y, 80.0);
+    }
+
+    #[test]
+    fn padding_margin_and_gap_offset_items() {
+        let style = FlexStyle {
+            direction: Direction::Column,
+            padding: LayoutSideOffsets::new(1.0, 2.0, 3.0, 4.0),
+            gap: 5.0,
+            align_items: Align::Stretch,
+            ..FlexStyle::default()
+        };
+        let margin = FlexItemStyle {
+            margin: LayoutSideOffsets::new(6.0, 7.0, 8.0, 9.0),
+            ..FlexItemStyle::default()
+        };
+        let origin = LayoutPoint::new(100.0, 200.0);
+        let rects = arrange(&style, LayoutRect::new(origin, LayoutSize::new(50.0, 100.0)), &[item(10.0, 10.0), styled(10.0, 10.0, margin)]);
+        assert_eq!(rects[0], LayoutRect::new(LayoutPoint::new(104.0, 201.0), LayoutSize::new(44.0, 10.0)));
+        // Below the first item, the gap and the top margin, inset by the left margin and narrowed by both side margins
+        assert_eq!(rects[1], LayoutRect::new(LayoutPoint::new(113.0, 222.0), LayoutSize::new(28.0, 10.0)));
+    }
+
+    // A widget that keeps its requested size apart from the bounds it was arranged to
+    struct Fixed {
+        size: LayoutSize,
+        area: LayoutRect
+    }
+
+    impl Layout for Fixed {
+        fn measure(&self) -> LayoutSize {
+            self.size
+        }
+
+        fn arrange(&mut self, bounds: LayoutRect) {
+            self.area = bounds;
+        }
+    }
+
+    fn arrange_widgets(style: &FlexStyle, bounds: LayoutRect, widgets: &mut [Fixed], item_style: FlexItemStyle) {
+        let items: Vec<FlexItem> = widgets.iter().map(|w| FlexItem::new(w.measure(), item_style)).collect();
+        for (widget, rect) in widgets.iter_mut().zip(arrange(style, bounds, &items)) {
+            widget.arrange(rect);
+        }
+    }
+
+    #[test]
+    fn rearranging_at_a_smaller_size_returns_to_the_natural_size() {
+        let style = row(Justify::Start, Align::Stretch);
+        let grow = FlexItemStyle {
+            grow: 1.0,
+            ..FlexItemStyle::default()
+        };
+        let mut widgets = [
+            Fixed { size: LayoutSize::new(10.0, 10.0), area: LayoutRect::zero() },
+            Fixed { size: LayoutSize::new(30.0, 10.0), area: LayoutRect::zero() }
+        ];
+
+        arrange_widgets(&style, bounds(200.0, 100.0), &mut widgets, grow);
+        assert_eq!(widgets[0].area.size, LayoutSize::new(90.0, 100.0));
+        assert_eq!(widgets[1].area.size, LayoutSize::new(110.0, 100.0));
+
+        arrange_widgets(&style, bounds(40.0, 20.0), &mut widgets, grow);
+        assert_eq!(widgets[0].area.size, LayoutSize::new(10.0, 20.0));
+        assert_eq!(widgets[1].area.size, LayoutSize::new(30.0, 20.0));
+
+        arrange_widgets(&style, bounds(20.0, 20.0), &mut widgets, grow);
+        assert_eq!(widgets[0].area.size, LayoutSize::new(5.0, 20.0));
+        assert_eq!(widgets[1].area.size, LayoutSize::new(15.0, 20.0));
+    }
+}
@@ -0,0 +1,22 @@
+pub mod state;
+pub mod text;
+pub mod fonts;
+#[cfg(feature = "shaping")]
+mod shaping;
+pub mod component;
+pub mod widget;
+pub mod input;
+pub mod controls;
+pub mod event;
+pub mod hit;
+pub mod container;
+pub mod layout;
+pub mod gl_state;
+pub mod offscreen;
+pub mod external;
+pub mod resources;
+pub mod ui;
+pub mod error;
+
+pub use ui::EmbeddedUi;
+pub use error::{Error, Result};
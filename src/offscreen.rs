@@ -0,0 +1,81 @@
+use std::rc::Rc;
+use gleam::gl::{self, Gl, GLuint};
+use webrender::api::units::DeviceIntSize;
+use crate::gl_state::GlStateGuard;
+
+// A framebuffer with a color texture that the UI can be rendered into,
+// so the host application can sample it like any other texture (for in-world screens, post-processing or fades).
+// The texture contains premultiplied alpha.
+pub struct OffscreenTarget {
+    gl: Rc<dyn Gl>,
+    framebuffer: GLuint,
+    texture: GLuint,
+    depth: GLuint,
+    size: DeviceIntSize
+}
+
+impl OffscreenTarget {
+    pub fn new(gl: Rc<dyn Gl>, size: DeviceIntSize) -> Self {
+        let framebuffer = gl.gen_framebuffers(1)[0];
+        let texture = gl.gen_textures(1)[0];
+        let depth = gl.gen_renderbuffers(1)[0];
+
+        let mut target = OffscreenTarget {
+            gl,
+            framebuffer,
+            texture,
+            depth,
+            size
+        };
+        target.allocate();
+        target
+    }
+
+    pub fn framebuffer(&self) -> GLuint {
+        self.framebuffer
+    }
+
+    pub fn texture(&self) -> GLuint {
+        self.texture
+    }
+
+    pub fn size(&self) -> DeviceIntSize {
+        self.size
+    }
+
+    pub fn resize(&mut self, size: DeviceIntSize) {
+        if size != self.size {
+            self.size = size;
+            self.allocate();
+        }
+    }
+
+    fn allocate(&mut self) {
+        let gl = &*self.gl;
+        let _guard = GlStateGuard::new(gl);
+
+        gl.bind_texture(gl::TEXTURE_2D, self.texture);
+        gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        gl.tex_image_2d(gl::TEXTURE_2D, 0, gl::RGBA8 as i32, self.size.width, self.size.height, 0, gl::RGBA, gl::UNSIGNED_BYTE, None);
+
+        // Webrender uses the depth buffer to reject hidden pixels of opaque items
+        gl.bind_renderbuffer(gl::RENDERBUFFER, self.depth);
+        gl.renderbuffer_storage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, self.size.width, self.size.height);
+
+        gl.bind_framebuffer(gl::FRAMEBUFFER, self.framebuffer);
+        gl.framebuffer_texture_2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.texture, 0);
+        gl.framebuffer_renderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, self.depth);
+        gl.bind_renderbuffer(gl::RENDERBUFFER, 0);
+    }
+}
+
+impl Drop for OffscreenTarget {
+    fn drop(&mut self) {
+        self.gl.delete_framebuffers(&[self.framebuffer]);
+        self.gl.delete_textures(&[self.texture]);
+        self.gl.delete_renderbuffers(&[self.depth]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use image::DynamicImage;
+use webrender::api::{ImageKey, ImageDescriptor, ImageDescriptorFlags, ImageData, ImageFormat, RenderApi, Transaction};
+use webrender::api::units::DeviceIntSize;
+use crate::error::{Error, Result};
+
+struct ImageEntry {
+    refs: usize,
+    path: Option<PathBuf>
+}
+
+// Reference counts shared between the manager and its handles
+#[derive(Default)]
+struct ImageStore {
+    entries: HashMap<ImageKey, ImageEntry>,
+    paths: HashMap<PathBuf, (ImageKey, DeviceIntSize)>,
+    unused: Vec<ImageKey>
+}
+
+// A reference to an image uploaded to webrender.
+// The image is deleted once the last handle is dropped.
+pub struct ImageHandle {
+    key: ImageKey,
+    size: DeviceIntSize,
+    store: Rc<RefCell<ImageStore>>
+}
+
+impl ImageHandle {
+    pub fn key(&self) -> ImageKey {
+        self.key
+    }
+
+    // The dimensions of the image in pixels
+    pub fn size(&self) -> DeviceIntSize {
+        self.size
+    }
+}
+
+impl Clone for ImageHandle {
+    fn clone(&self) -> Self {
+        if let Some(entry) = self.store.borrow_mut().entries.get_mut(&self.key) {
+            entry.refs += 1;
+        }
+
+        ImageHandle {
+            key: self.key,
+            size: self.size,
+            store: self.store.clone()
+        }
+    }
+}
+
+impl Drop for ImageHandle {
+    fn drop(&mut self) {
+        let mut store = self.store.borrow_mut();
+        let released = match store.entries.get_mut(&self.key) {
+            Some(entry) => {
+                entry.refs -= 1;
+                entry.refs == 0
+            }
+            None => false
+        };
+
+        if released {
+            if let Some(path) = store.entries.remove(&self.key).and_then(|e| e.path) {
+                store.paths.remove(&path);
+            }
+            store.unused.push(self.key);
+        }
+    }
+}
+
+// Webrender expects premultiplied alpha for AlphaType::PremultipliedAlpha
+fn premultiply(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+// Every color type is converted to BGRA8, which is the format webrender handles natively.
+// The images are premultiplied and only flagged as opaque if no pixel is transparent.
+fn decode(image: DynamicImage) -> (ImageDescriptor, ImageData) {
+    let has_alpha = image.color().has_alpha();
+    let bgra = image.to_bgra8();
+    let (width, height) = bgra.dimensions();
+    let mut data = bgra.into_raw();
+
+    let opaque = !has_alpha || data.chunks_exact(4).all(|pixel| pixel[3] == 255);
+    let flags = if opaque {
+        ImageDescriptorFlags::IS_OPAQUE
+    } else {
+        premultiply(&mut data);
+        ImageDescriptorFlags::empty()
+    };
+
+    let img_descr = ImageDescriptor::new(width as i32, height as i32, ImageFormat::BGRA8, flags);
+    (img_descr, ImageData::new(data))
+}
+
+// Uploads images to webrender and deletes them again when they are no longer used.
+// Images loaded from the same path share a single ImageKey.
+#[derive(Default)]
+pub struct ImageManager {
+    store: Rc<RefCell<ImageStore>>
+}
+
+impl ImageManager {
+    pub fn new() -> Self {
+        ImageManager::default()
+    }
+
+    fn add(&mut self, key: ImageKey, size: DeviceIntSize, path: Option<PathBuf>) -> ImageHandle {
+        let mut store = self.store.borrow_mut();
+        store.entries.insert(key, ImageEntry {
+            refs: 1,
+            path: path.clone()
+        });
+        if let Some(path) = path {
+            store.paths.insert(path, (key, size));
+        }
+
+        ImageHandle {
+            key,
+            size,
+            store: self.store.clone()
+        }
+    }
+
+    fn upload(&mut self, api: &RenderApi, txn: &mut Transaction, image: DynamicImage, path: Option<PathBuf>) -> ImageHandle {
+        let key = api.generate_image_key();
+        let (descriptor, data) = decode(image);
+        txn.add_image(key, descriptor, data, None);
+        self.add(key, descriptor.size, path)
+    }
+
+    pub fn load_file(&mut self, api: &RenderApi, txn: &mut Transaction, path: &Path) -> Result<ImageHandle> {
+        let cached = self.store.borrow().paths.get(path).copied();
+        if let Some((key, size)) = cached {
+            if let Some(entry) = self.store.borrow_mut().entries.get_mut(&key) {
+                entry.refs += 1;
+            }
+
+            return Ok(ImageHandle {
+                key,
+                size,
+                store: self.store.clone()
+            })
+        }
+
+        let image_file = File::open(path).map_err(|e| Error::MissingFile(path.to_path_buf(), e))?;
+        let image_reader = BufReader::new(image_file);
+        let format = image::ImageFormat::from_path(path)?;
+        let image = image::load(image_reader, format)?;
+
+        Ok(self.upload(api, txn, image, Some(path.to_path_buf())))
+    }
+
+    pub fn load_bytes(&mut self, api: &RenderApi, txn: &mut Transaction, bytes: &[u8]) -> Result<ImageHandle> {
+        let image = image::load_from_memory(bytes)?;
+        Ok(self.upload(api, txn, image, None))
+    }
+
+    // Deletes the images whose handles have all been dropped
+    pub fn collect_garbage(&mut self, txn: &mut Transaction) {
+        for key in self.store.borrow_mut().unused.drain(..) {
+            txn.delete_image(key);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use rustybuzz::{Direction, Tag, UnicodeBuffer};
+use unicode_script::Script;
+use webrender::api::units::LayoutVector2D;
+use crate::text::ShapedGlyph;
+
+// Shapes a run of text that uses a single font, script and direction, returns None if the font data cannot be parsed.
+// The glyphs are returned in logical order, even if the shaper placed them right-to-left.
+pub fn shape(data: &[u8], size: f32, text: &str, script: Script, rtl: bool) -> Option<Vec<ShapedGlyph>> {
+    let face = rustybuzz::Face::from_slice(data, 0)?;
+    let units_per_em = ttf_parser::Face::from_slice(data, 0).ok()?.units_per_em()?;
+    let scale = size / f32::from(units_per_em);
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+    if let Some(script) = rustybuzz::Script::from_iso15924_tag(Tag::from_bytes_lossy(script.short_name().as_bytes())) {
+        buffer.set_script(script);
+    }
+
+    let output = rustybuzz::shape(&face, &[], buffer);
+    let mut glyphs: Vec<ShapedGlyph> = output.glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| {
+            ShapedGlyph {
+                index: info.codepoint,
+                cluster: info.cluster as usize,
+                advance: Some(position.x_advance as f32 * scale),
+                // Font units point up, layout units point down
+                offset: LayoutVector2D::new(position.x_offset as f32 * scale, -position.y_offset as f32 * scale)
+            }
+        })
+        .collect();
+
+    if rtl {
+        glyphs.reverse();
+    }
+    Some(glyphs)
+}
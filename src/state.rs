@@ -1,10 +1,17 @@
 use std::cell::RefCell;
 
 pub type Selector<'a, O> = Box<dyn Fn() -> O + 'a>;
+// Turns values produced by a component into messages for a store
+pub type Dispatcher<'a, I> = Box<dyn Fn(I) + 'a>;
 
 pub trait Store<T, Msg> {
     fn update(&self, msg: Msg);
     fn selector<'a, F, O>(&'a self, sel: F) -> Selector<'a, O> where F: Fn(&T) -> O, F: 'a;
+
+    // Sends a message made from every value that is passed to the dispatcher
+    fn dispatcher<'a, F, I>(&'a self, msg: F) -> Dispatcher<'a, I> where F: Fn(I) -> Msg, F: 'a, Self: Sized {
+        Box::new(move |input| self.update(msg(input)))
+    }
 }
 
 pub struct ImmutableStore<T, Msg> {
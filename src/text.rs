@@ -1,42 +1,848 @@
-use webrender::api::{GlyphDimensions, FontKey, FontInstanceKey, RenderApi};
-use webrender::api::units::{LayoutSize};
+use std::ops::Range;
+use webrender::api::{GlyphDimensions, GlyphInstance, FontInstanceKey, RenderApi};
+use webrender::api::units::{LayoutSize, LayoutPoint, LayoutRect, LayoutVector2D};
+use xi_unicode::LineBreakIterator;
+use unicode_bidi::{BidiInfo, Level};
+use unicode_script::{Script, UnicodeScript};
+use crate::fonts::FontInstance;
+
+// Index of the .notdef glyph, which fonts draw as a box (tofu)
+const NOTDEF_GLYPH: u32 = 0;
+const ELLIPSIS: &str = "\u{2026}";
+const ELLIPSIS_FALLBACK: &str = "...";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    // Stretches the spaces of every line except the last one of a paragraph to fill the width
+    Justify
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom
+}
+
+// The base direction of the paragraphs of a text, Auto takes it from the first character with a strong direction
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextDirection {
+    Auto,
+    LeftToRight,
+    RightToLeft
+}
+
+// What happens to text that does not fit into the box of a label
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overflow {
+    Visible,
+    Clip,
+    Ellipsis
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParagraphStyle {
+    // Distance between baselines as a multiple of the line height of the font
+    pub line_height: f32,
+    // Lines are wrapped at word boundaries so they do not get wider than this
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    pub overflow: Overflow,
+    pub direction: TextDirection
+}
+
+impl Default for ParagraphStyle {
+    fn default() -> Self {
+        ParagraphStyle {
+            line_height: 1.0,
+            max_width: None,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            overflow: Overflow::Visible,
+            direction: TextDirection::Auto
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Glyph {
+    // The first character the glyph was created from and its byte offset in the text
+    pub character: char,
+    pub cluster: usize,
+    pub index: u32,
+    // Index into the fonts of the LayoutedText
+    pub font: usize,
+    pub dimensions: GlyphDimensions,
+    // Moves the glyph without affecting the position of the following glyphs
+    pub offset: LayoutVector2D,
+    // Bidi embedding level, odd levels are right-to-left
+    pub level: u8
+}
+
+// A glyph placed by the shaper, without shaping every character becomes a glyph that uses the advance of its dimensions
+pub(crate) struct ShapedGlyph {
+    pub index: u32,
+    // Byte offset in the shaped text
+    pub cluster: usize,
+    pub advance: Option<f32>,
+    pub offset: LayoutVector2D
+}
+
+impl Glyph {
+    // Control characters are not drawn, only tabs take up space
+    pub fn is_visible(&self) -> bool {
+        !self.character.is_control()
+    }
+}
+
+// A range of glyphs that is drawn on the same baseline
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    pub start: usize,
+    pub end: usize,
+    // Trailing whitespace does not count towards the width
+    pub width: f32,
+    // Whether the line belongs to a right-to-left paragraph
+    pub rtl: bool
+}
+
+// A glyph and the point on the baseline where it starts
+#[derive(Clone, Copy, Debug)]
+pub struct PlacedGlyph {
+    pub glyph: Glyph,
+    // Index into the glyphs of the LayoutedText, None for the glyphs of an ellipsis
+    pub source: Option<usize>,
+    pub line: usize,
+    pub origin: LayoutPoint
+}
+
+// Consecutive glyphs that use the same font instance and can be pushed with a single push_text
+pub struct GlyphRun {
+    pub inst_key: FontInstanceKey,
+    pub glyphs: Vec<GlyphInstance>
+}
 
 pub struct LayoutedText {
     pub text: String,
-    pub indices: Vec<u32>,
-    pub dimensions: Vec<GlyphDimensions>,
-    pub font_key: FontKey,
-    pub inst_key: FontInstanceKey,
+    pub style: ParagraphStyle,
+    pub fonts: Vec<FontInstance>,
+    pub glyphs: Vec<Glyph>,
+    // Only resolved if the overflow of the style is Ellipsis
+    pub ellipsis: Vec<Glyph>,
+    pub lines: Vec<Line>,
+    pub line_height: f32,
+    // Distance from the top of a line to its baseline
+    pub baseline: f32,
     pub size: LayoutSize
 }
 
 impl LayoutedText {
-    pub fn new(text: String, font_key: FontKey, inst_key: FontInstanceKey, api: &RenderApi) -> Self {
-        let indices: Vec<u32> = api
-            .get_glyph_indices(font_key, &text)
-            .iter()
-            .filter_map(|&x| x)
-            .collect();
-        let dimensions: Vec<GlyphDimensions> = api
-            .get_glyph_dimensions(inst_key, indices.clone())
-            .iter()
-            .filter_map(|&x| x)
-            .collect();
+    // There has to be at least one font, the first one determines the line height and baseline
+    pub fn new(text: String, fonts: Vec<FontInstance>, style: &ParagraphStyle, api: &RenderApi) -> Self {
+        let base_level = match style.direction {
+            TextDirection::Auto => None,
+            TextDirection::LeftToRight => Some(Level::ltr()),
+            TextDirection::RightToLeft => Some(Level::rtl())
+        };
+        let bidi = BidiInfo::new(&text, base_level);
+        let mut glyphs = resolve_glyphs(&text, &bidi.levels, &fonts, api);
+        for glyph in &mut glyphs {
+            glyph.level = bidi.levels.get(glyph.cluster).map_or(0, Level::number);
+        }
+        let ellipsis = if style.overflow == Overflow::Ellipsis {
+            let ellipsis = resolve_glyphs(ELLIPSIS, &[], &fonts, api);
+            if ellipsis.iter().all(|g| g.index != NOTDEF_GLYPH) {
+                ellipsis
+            } else {
+                resolve_glyphs(ELLIPSIS_FALLBACK, &[], &fonts, api)
+            }
+        } else {
+            Vec::new()
+        };
 
-        let (size_x, size_y) = dimensions.iter().fold((0.0, 0.0), |(x, y), &g| {
-            let dy = (g.height + (g.height - g.top)) as f32;
-            (x + g.advance, f32::max(y, dy))
-        });
+        let mut lines = break_lines(&text, &glyphs, style.max_width);
+        for line in &mut lines {
+            let offset = glyphs.get(line.start).map_or(text.len(), |g| g.cluster);
+            line.rtl = bidi.paragraphs
+                .iter()
+                .find(|p| p.range.contains(&offset))
+                .or_else(|| bidi.paragraphs.last())
+                .map_or(style.direction == TextDirection::RightToLeft, |p| p.level.is_rtl());
+        }
+
+        // The metrics of the first font are used even if glyphs come from fallback fonts, so the lines of all labels with the same font line up.
+        // Extra line height is split evenly above and below the glyphs.
+        let metrics = fonts[0].metrics;
+        let line_height = metrics.line_height() * style.line_height;
+        let baseline = (line_height - metrics.ascent - metrics.descent) / 2.0 + metrics.ascent;
 
-        let size = LayoutSize::new(size_x as f32, size_y as f32);
+        let width = lines.iter().fold(0.0, |w, l| f32::max(w, l.width));
+        let size = LayoutSize::new(width, lines.len() as f32 * line_height);
 
         LayoutedText {
             text,
-            indices,
-            dimensions,
-            font_key,
-            inst_key,
+            style: *style,
+            fonts,
+            glyphs,
+            ellipsis,
+            lines,
+            line_height,
+            baseline,
             size
         }
     }
-}
\ No newline at end of file
+
+    // The number of lines that are drawn into a box of the given height
+    fn visible_lines(&self, height: f32) -> usize {
+        match self.style.overflow {
+            Overflow::Ellipsis => {
+                let fitting = (height / self.line_height).floor() as usize;
+                usize::min(usize::max(fitting, 1), self.lines.len())
+            }
+            _ => self.lines.len()
+        }
+    }
+
+    // The area the glyphs cover when they are aligned inside the bounds
+    pub fn text_rect(&self, bounds: LayoutRect) -> LayoutRect {
+        let height = self.visible_lines(bounds.size.height) as f32 * self.line_height;
+        let width = match self.style.overflow {
+            Overflow::Ellipsis => f32::min(self.size.width, bounds.size.width),
+            _ => self.size.width
+        };
+
+        let x = match self.style.align {
+            TextAlign::Left | TextAlign::Justify => 0.0,
+            TextAlign::Center => (bounds.size.width - width) / 2.0,
+            TextAlign::Right => bounds.size.width - width
+        };
+        let y = match self.style.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => (bounds.size.height - height) / 2.0,
+            VerticalAlign::Bottom => bounds.size.height - height
+        };
+
+        LayoutRect::new(bounds.origin + LayoutVector2D::new(x, y), LayoutSize::new(width, height))
+    }
+
+    // Places the glyphs line by line inside the bounds according to the alignment of the style.
+    // With Ellipsis overflow, lines that are too long and the last line that fits are cut off and end in an ellipsis.
+    pub fn place_glyphs(&self, bounds: LayoutRect) -> Vec<PlacedGlyph> {
+        let visible = self.visible_lines(bounds.size.height);
+        let top = self.text_rect(bounds).origin.y;
+
+        let mut placed = Vec::with_capacity(self.glyphs.len());
+        for (i, line) in self.lines[..visible].iter().enumerate() {
+            let baseline = top + i as f32 * self.line_height + self.baseline;
+            let cut_off = i + 1 == visible && visible < self.lines.len();
+            let (end, ellipsis) = self.fit_line(line, bounds.size.width, cut_off);
+            let width = if ellipsis {
+                line_width(&self.glyphs[line.start..end]) + self.ellipsis.iter().map(|g| g.dimensions.advance).sum::<f32>()
+            } else {
+                line.width
+            };
+
+            let free = bounds.size.width - width;
+            let (offset, spacing) = match self.style.align {
+                TextAlign::Left => (0.0, 0.0),
+                TextAlign::Center => (free / 2.0, 0.0),
+                TextAlign::Right => (free, 0.0),
+                TextAlign::Justify => {
+                    let last_of_paragraph = i + 1 == self.lines.len()
+                        || matches!(self.glyphs[line.start..line.end].last(), Some(g) if g.character == '\n');
+                    let trimmed = trimmed_len(&self.glyphs[line.start..end]);
+                    let spaces = self.glyphs[line.start..line.start + trimmed].iter().filter(|g| g.character == ' ').count();
+                    if last_of_paragraph || ellipsis || spaces == 0 || free <= 0.0 {
+                        (0.0, 0.0)
+                    } else {
+                        (0.0, free / spaces as f32)
+                    }
+                }
+            };
+
+            // Trailing whitespace does not count towards the width of the line and is placed after the other glyphs
+            // in the direction of the paragraph, lines that end in an ellipsis drop it.
+            let trimmed = line.start + trimmed_len(&self.glyphs[line.start..end]);
+            let trailing = if ellipsis { trimmed..trimmed } else { trimmed..end };
+            let trailing_width: f32 = self.glyphs[trailing.clone()].iter().map(|g| g.dimensions.advance).sum();
+            let line_glyphs = visual_order(&self.glyphs[line.start..trimmed], line.rtl)
+                .into_iter()
+                .map(|i| (Some(line.start + i), self.glyphs[line.start + i]));
+            let ellipsis_glyphs = self.ellipsis.iter().take(if ellipsis { self.ellipsis.len() } else { 0 }).map(|&g| (None, g));
+
+            let mut x = bounds.origin.x + offset;
+            let glyphs: Vec<(Option<usize>, Glyph)> = if line.rtl {
+                x -= trailing_width;
+                trailing.rev().map(|i| (Some(i), self.glyphs[i])).chain(ellipsis_glyphs).chain(line_glyphs).collect()
+            } else {
+                line_glyphs.chain(trailing.map(|i| (Some(i), self.glyphs[i]))).chain(ellipsis_glyphs).collect()
+            };
+
+            for (source, glyph) in glyphs {
+                placed.push(PlacedGlyph {
+                    glyph,
+                    source,
+                    line: i,
+                    origin: LayoutPoint::new(x, baseline)
+                });
+                x += glyph.dimensions.advance;
+                if glyph.character == ' ' {
+                    x += spacing;
+                }
+            }
+        }
+
+        placed
+    }
+
+    // Places the glyphs like place_glyphs, split into one run per font
+    pub fn glyph_runs(&self, bounds: LayoutRect) -> Vec<GlyphRun> {
+        let mut runs: Vec<GlyphRun> = Vec::new();
+        for placed in self.place_glyphs(bounds) {
+            if !placed.glyph.is_visible() {
+                continue
+            }
+
+            let instance = GlyphInstance {
+                index: placed.glyph.index,
+                point: placed.origin + placed.glyph.offset
+            };
+            let inst_key = self.fonts[placed.glyph.font].inst_key;
+            match runs.last_mut() {
+                Some(run) if run.inst_key == inst_key => run.glyphs.push(instance),
+                _ => runs.push(GlyphRun {
+                    inst_key,
+                    glyphs: vec![instance]
+                })
+            }
+        }
+
+        runs
+    }
+
+    fn line_top(&self, bounds: LayoutRect, line: usize) -> f32 {
+        self.text_rect(bounds).origin.y + line as f32 * self.line_height
+    }
+
+    // The byte offset of the character after the glyph
+    fn next_offset(&self, glyph: usize) -> usize {
+        self.glyphs.get(glyph + 1).map_or(self.text.len(), |g| g.cluster)
+    }
+
+    // Returns the line and x coordinate of a caret in front of the character at the byte offset
+    pub fn caret_position(&self, bounds: LayoutRect, offset: usize) -> (usize, f32) {
+        let placed = self.place_glyphs(bounds);
+        let glyph = self.glyphs.iter().position(|g| g.cluster >= offset);
+        if let Some(glyph) = glyph {
+            if let Some(p) = placed.iter().find(|p| p.source == Some(glyph)) {
+                let rtl = p.glyph.level % 2 == 1;
+                return (p.line, if rtl { p.origin.x + p.glyph.dimensions.advance } else { p.origin.x })
+            }
+        }
+
+        // A text that ends in a line break has an empty last line, the caret goes to its start
+        let visible = self.visible_lines(bounds.size.height);
+        if matches!(self.glyphs.last(), Some(g) if g.character == '\n') && self.lines.len() == visible {
+            return (self.lines.len() - 1, self.empty_line_x(bounds))
+        }
+
+        // Behind the last glyph that is drawn
+        match placed.iter().filter(|p| p.source.is_some()).max_by_key(|p| p.source) {
+            Some(p) => {
+                let rtl = p.glyph.level % 2 == 1;
+                (p.line, if rtl { p.origin.x } else { p.origin.x + p.glyph.dimensions.advance })
+            }
+            None => (0, self.text_rect(bounds).origin.x)
+        }
+    }
+
+    // Where an empty line starts when it is aligned inside the bounds
+    fn empty_line_x(&self, bounds: LayoutRect) -> f32 {
+        match self.style.align {
+            TextAlign::Left | TextAlign::Justify => bounds.min_x(),
+            TextAlign::Center => bounds.min_x() + bounds.size.width / 2.0,
+            TextAlign::Right => bounds.max_x()
+        }
+    }
+
+    // The area of a caret in front of the character at the byte offset, without a width
+    pub fn caret_rect(&self, bounds: LayoutRect, offset: usize) -> LayoutRect {
+        let (line, x) = self.caret_position(bounds, offset);
+        LayoutRect::new(LayoutPoint::new(x, self.line_top(bounds, line)), LayoutSize::new(0.0, self.line_height))
+    }
+
+    // Returns the byte offset of the character boundary closest to the point
+    pub fn offset_at(&self, bounds: LayoutRect, point: LayoutPoint) -> usize {
+        let visible = self.visible_lines(bounds.size.height);
+        let line = ((point.y - self.line_top(bounds, 0)) / self.line_height).floor();
+        let line = f32::min(f32::max(line, 0.0), (visible - 1) as f32) as usize;
+
+        let placed: Vec<PlacedGlyph> = self.place_glyphs(bounds)
+            .into_iter()
+            .filter(|p| p.line == line && p.source.is_some())
+            .collect();
+        for p in &placed {
+            let glyph = p.source.unwrap_or(0);
+            let advance = p.glyph.dimensions.advance;
+            if point.x < p.origin.x + advance {
+                let rtl = p.glyph.level % 2 == 1;
+                let left_half = point.x < p.origin.x + advance / 2.0;
+                return if left_half != rtl { self.glyphs[glyph].cluster } else { self.next_offset(glyph) }
+            }
+        }
+
+        // Right of the line, a caret at the end of a line stays in front of its line break or the space it was wrapped at
+        let line = self.lines[line];
+        let rtl_line = line.rtl;
+        match self.glyphs[line.start..line.end].last() {
+            _ if rtl_line => self.glyphs.get(line.start).map_or(self.text.len(), |g| g.cluster),
+            Some(g) if g.character == '\n' => g.cluster,
+            Some(g) if g.character.is_whitespace() && line.end < self.glyphs.len() => g.cluster,
+            Some(_) => self.next_offset(line.end - 1),
+            None => self.glyphs.get(line.start).map_or(self.text.len(), |g| g.cluster)
+        }
+    }
+
+    // The areas covered by the characters in the byte range, one rectangle per line and direction run
+    pub fn selection_rects(&self, bounds: LayoutRect, range: Range<usize>) -> Vec<LayoutRect> {
+        let mut rects: Vec<(usize, LayoutRect)> = Vec::new();
+        for p in self.place_glyphs(bounds) {
+            let selected = match p.source {
+                Some(glyph) => range.contains(&self.glyphs[glyph].cluster),
+                None => false
+            };
+            if !selected {
+                continue
+            }
+
+            let rect = LayoutRect::new(LayoutPoint::new(p.origin.x, self.line_top(bounds, p.line)), LayoutSize::new(p.glyph.dimensions.advance, self.line_height));
+            match rects.last_mut() {
+                Some((line, last)) if *line == p.line && (last.max_x() - rect.min_x()).abs() < 0.01 => *last = last.union(&rect),
+                _ => rects.push((p.line, rect))
+            }
+        }
+        rects.into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    // Returns the end of the glyphs of the line that are drawn and whether an ellipsis follows them
+    fn fit_line(&self, line: &Line, width: f32, cut_off: bool) -> (usize, bool) {
+        if self.style.overflow != Overflow::Ellipsis || (!cut_off && line.width <= width) {
+            return (line.end, false)
+        }
+
+        let available = width - self.ellipsis.iter().map(|g| g.dimensions.advance).sum::<f32>();
+        let mut x = 0.0;
+        let mut end = line.start;
+        for glyph in &self.glyphs[line.start..line.end] {
+            if x + glyph.dimensions.advance > available {
+                break
+            }
+            x += glyph.dimensions.advance;
+            end += 1;
+        }
+        (end, true)
+    }
+}
+
+// Looks the characters up in the fonts in order, characters that no font has a glyph for become tofu of the first font.
+// Consecutive characters that use the same font are shaped together if shaping is enabled.
+// A character with the font that has a glyph for it and the properties that split shaping runs
+#[derive(Copy, Clone)]
+struct ResolvedChar {
+    offset: usize,
+    font: usize,
+    index: u32,
+    level: Level,
+    script: Script
+}
+
+// The script of every character, characters that are shared between scripts (like spaces, digits and combining marks) take the script of the text around them
+fn resolve_scripts(text: &str) -> Vec<Script> {
+    let is_shared = |script: Script| matches!(script, Script::Common | Script::Inherited | Script::Unknown);
+    let mut current = text.chars().map(|c| c.script()).find(|&s| !is_shared(s)).unwrap_or(Script::Common);
+    text.chars()
+        .map(|c| {
+            let script = c.script();
+            if !is_shared(script) {
+                current = script;
+            }
+            current
+        })
+        .collect()
+}
+
+// Levels holds the bidi level of every byte of the text, missing levels are left-to-right
+fn resolve_glyphs(text: &str, levels: &[Level], fonts: &[FontInstance], api: &RenderApi) -> Vec<Glyph> {
+    let mut resolved: Vec<Option<(usize, u32)>> = text.chars()
+        .map(|c| if c.is_control() { Some((0, NOTDEF_GLYPH)) } else { None })
+        .collect();
+    for (font, instance) in fonts.iter().enumerate() {
+        if resolved.iter().all(Option::is_some) {
+            break
+        }
+
+        let indices = api.get_glyph_indices(instance.font_key, text);
+        for (slot, index) in resolved.iter_mut().zip(indices) {
+            match index {
+                Some(index) if slot.is_none() && index != NOTDEF_GLYPH => *slot = Some((font, index)),
+                _ => ()
+            }
+        }
+    }
+
+    let resolved: Vec<ResolvedChar> = text.char_indices()
+        .zip(resolved)
+        .zip(resolve_scripts(text))
+        .map(|(((offset, _), r), script)| {
+            let (font, index) = r.unwrap_or((0, NOTDEF_GLYPH));
+            ResolvedChar {
+                offset,
+                font,
+                index,
+                level: levels.get(offset).copied().unwrap_or_else(Level::ltr),
+                script
+            }
+        })
+        .collect();
+
+    // Control characters and tofu are never shaped, they end a segment.
+    // A segment also ends where the font, the script or the bidi level changes, because each is shaped with a single direction and script.
+    let mut shaped: Vec<(usize, ShapedGlyph)> = Vec::with_capacity(resolved.len());
+    let mut start = 0;
+    while start < resolved.len() {
+        let first = resolved[start];
+        let shapeable = |r: &ResolvedChar| r.font == first.font && r.index != NOTDEF_GLYPH && r.level == first.level && r.script == first.script;
+        let len = resolved[start..].iter().take_while(|r| shapeable(r)).count();
+        if len == 0 {
+            shaped.push((first.font, ShapedGlyph {
+                index: first.index,
+                cluster: first.offset,
+                advance: None,
+                offset: LayoutVector2D::zero()
+            }));
+            start += 1;
+            continue
+        }
+
+        let end = resolved.get(start + len).map_or(text.len(), |r| r.offset);
+        match shape_segment(&fonts[first.font], &text[first.offset..end], first.script, first.level.is_rtl()) {
+            Some(glyphs) => shaped.extend(glyphs.into_iter().map(|g| (first.font, ShapedGlyph {
+                cluster: g.cluster + first.offset,
+                ..g
+            }))),
+            None => shaped.extend(resolved[start..start + len].iter().map(|r| (first.font, ShapedGlyph {
+                index: r.index,
+                cluster: r.offset,
+                advance: None,
+                offset: LayoutVector2D::zero()
+            })))
+        }
+        start += len;
+    }
+
+    let faces: Vec<Option<ttf_parser::Face>> = fonts.iter().map(|f| ttf_parser::Face::from_slice(&f.data, 0).ok()).collect();
+    let mut dimensions: Vec<_> = fonts.iter().enumerate().map(|(font, instance)| {
+        let indices: Vec<u32> = shaped.iter().filter(|g| g.0 == font).map(|g| g.1.index).collect();
+        if indices.is_empty() {
+            Vec::new().into_iter()
+        } else {
+            api.get_glyph_dimensions(instance.inst_key, indices).into_iter()
+        }
+    }).collect();
+
+    shaped.into_iter().map(|(font, glyph)| {
+        let character = text[glyph.cluster..].chars().next().unwrap_or(' ');
+        let mut dimensions = match dimensions[font].next().flatten() {
+            Some(dimensions) if !character.is_control() => dimensions,
+            // Glyphs without an outline, like spaces, have no dimensions
+            _ => empty_glyph(character, glyph.index, fonts[font].size, faces[font].as_ref())
+        };
+        if let Some(advance) = glyph.advance {
+            dimensions.advance = advance;
+        }
+
+        Glyph {
+            character,
+            cluster: glyph.cluster,
+            index: glyph.index,
+            font,
+            dimensions,
+            offset: glyph.offset,
+            level: 0
+        }
+    }).collect()
+}
+
+#[cfg(feature = "shaping")]
+fn shape_segment(font: &FontInstance, text: &str, script: Script, rtl: bool) -> Option<Vec<ShapedGlyph>> {
+    crate::shaping::shape(&font.data, font.size, text, script, rtl)
+}
+
+#[cfg(not(feature = "shaping"))]
+fn shape_segment(_font: &FontInstance, _text: &str, _script: Script, _rtl: bool) -> Option<Vec<ShapedGlyph>> {
+    None
+}
+
+// Tabs advance by this many spaces
+const TAB_WIDTH: f32 = 4.0;
+
+// The advance of a glyph from the hmtx table of the font, in layout units
+fn glyph_advance(face: &ttf_parser::Face, index: u32, font_size: f32) -> Option<f32> {
+    let advance = face.glyph_hor_advance(ttf_parser::GlyphId(index as u16))?;
+    Some(f32::from(advance) * font_size / f32::from(face.units_per_em()?))
+}
+
+// Webrender does not report an advance for glyphs without an outline, so it is read from the font.
+// Fonts that cannot be parsed fall back to the usual width of a space.
+fn empty_glyph(character: char, index: u32, font_size: f32, face: Option<&ttf_parser::Face>) -> GlyphDimensions {
+    let space = || face
+        .and_then(|face| glyph_advance(face, u32::from(face.glyph_index(' ')?.0), font_size))
+        .unwrap_or(font_size / 4.0);
+    let advance = match character {
+        '\t' => space() * TAB_WIDTH,
+        c if c.is_control() => 0.0,
+        _ => face.and_then(|face| glyph_advance(face, index, font_size)).unwrap_or_else(space)
+    };
+    GlyphDimensions {
+        left: 0,
+        top: 0,
+        width: 0,
+        height: 0,
+        advance
+    }
+}
+
+// Returns the indices of the glyphs of a line in the order they are drawn from left to right.
+// Starting with the highest bidi level, every sequence of glyphs at that level or above is reversed (rule L2 of UAX #9).
+fn visual_order(glyphs: &[Glyph], rtl: bool) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..glyphs.len()).collect();
+    let highest = glyphs.iter().map(|g| g.level).max().unwrap_or(0);
+    let lowest_odd = glyphs.iter().map(|g| g.level).filter(|l| l % 2 == 1).min();
+    let lowest_odd = match lowest_odd {
+        Some(level) => level,
+        None if rtl => 1,
+        None => return order
+    };
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            let start = i;
+            while i < order.len() && glyphs[order[i]].level >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+            i += 1;
+        }
+    }
+    order
+}
+
+// Number of glyphs without the trailing whitespace
+fn trimmed_len(glyphs: &[Glyph]) -> usize {
+    glyphs.iter().rposition(|g| !g.character.is_whitespace()).map_or(0, |i| i + 1)
+}
+
+fn line_width(glyphs: &[Glyph]) -> f32 {
+    glyphs[..trimmed_len(glyphs)].iter().map(|g| g.dimensions.advance).sum()
+}
+
+// Splits the glyphs into lines at explicit line breaks and, if a maximum width is given,
+// at the last break opportunity before a line gets too wide. Words that are wider than the maximum get a line of their own.
+fn break_lines(text: &str, glyphs: &[Glyph], max_width: Option<f32>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut segment_start = 0;
+
+    for (offset, hard) in LineBreakIterator::new(text) {
+        // Break opportunities are byte offsets, a segment ends before the first glyph at or after the offset
+        let mut segment_end = segment_start;
+        while segment_end < glyphs.len() && glyphs[segment_end].cluster < offset {
+            segment_end += 1;
+        }
+
+        if let Some(max_width) = max_width {
+            if segment_start > line_start && line_width(&glyphs[line_start..segment_end]) > max_width {
+                lines.push(Line {
+                    start: line_start,
+                    end: segment_start,
+                    width: line_width(&glyphs[line_start..segment_start]),
+                    rtl: false
+                });
+                line_start = segment_start;
+            }
+        }
+
+        if hard {
+            lines.push(Line {
+                start: line_start,
+                end: segment_end,
+                width: line_width(&glyphs[line_start..segment_end]),
+                rtl: false
+            });
+            line_start = segment_end;
+        }
+        segment_start = segment_end;
+    }
+
+    // A trailing line break starts another, empty line
+    if lines.is_empty() || text.ends_with('\n') {
+        lines.push(Line {
+            start: glyphs.len(),
+            end: glyphs.len(),
+            width: 0.0,
+            rtl: false
+        });
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVANCE: f32 = 10.0;
+
+    // Every visible character gets a glyph of the same width, levels are given per character
+    fn glyphs(text: &str, levels: &[u8]) -> Vec<Glyph> {
+        text.char_indices().enumerate().map(|(i, (cluster, character))| Glyph {
+            character,
+            cluster,
+            index: 1,
+            font: 0,
+            dimensions: GlyphDimensions {
+                left: 0,
+                top: 0,
+                width: 0,
+                height: 0,
+                advance: if character.is_control() { 0.0 } else { ADVANCE }
+            },
+            offset: LayoutVector2D::zero(),
+            level: levels.get(i).copied().unwrap_or(0)
+        }).collect()
+    }
+
+    fn layouted(text: &str, max_width: Option<f32>) -> LayoutedText {
+        let glyphs = glyphs(text, &[]);
+        let lines = break_lines(text, &glyphs, max_width);
+        let width = lines.iter().fold(0.0, |w, l| f32::max(w, l.width));
+        LayoutedText {
+            text: text.to_string(),
+            style: ParagraphStyle {
+                max_width,
+                ..ParagraphStyle::default()
+            },
+            fonts: Vec::new(),
+            glyphs,
+            ellipsis: Vec::new(),
+            size: LayoutSize::new(width, lines.len() as f32 * 20.0),
+            lines,
+            line_height: 20.0,
+            baseline: 15.0
+        }
+    }
+
+    fn bounds() -> LayoutRect {
+        LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(200.0, 200.0))
+    }
+
+    #[test]
+    fn caret_after_trailing_line_break_is_on_the_empty_line() {
+        let text = layouted("ab\n", None);
+        assert_eq!(text.caret_position(bounds(), 3), (1, 0.0));
+        assert_eq!(text.caret_position(bounds(), 2), (0, 20.0));
+    }
+
+    #[test]
+    fn caret_at_the_end_without_line_break() {
+        let text = layouted("ab", None);
+        assert_eq!(text.caret_position(bounds(), 2), (0, 20.0));
+        assert_eq!(layouted("", None).caret_position(bounds(), 0), (0, 0.0));
+    }
+
+    #[test]
+    fn offset_right_of_a_line_stays_in_front_of_its_line_break() {
+        let text = layouted("ab\ncd\n", None);
+        assert_eq!(text.offset_at(bounds(), LayoutPoint::new(150.0, 5.0)), 2);
+        assert_eq!(text.offset_at(bounds(), LayoutPoint::new(150.0, 25.0)), 5);
+        assert_eq!(text.offset_at(bounds(), LayoutPoint::new(150.0, 45.0)), 6);
+    }
+
+    #[test]
+    fn offset_right_of_a_wrapped_line_stays_in_front_of_the_space() {
+        let text = layouted("ab cd", Some(25.0));
+        assert_eq!(text.offset_at(bounds(), LayoutPoint::new(150.0, 5.0)), 2);
+        assert_eq!(text.offset_at(bounds(), LayoutPoint::new(150.0, 25.0)), 5);
+    }
+
+    fn line_ranges(text: &str, max_width: Option<f32>) -> Vec<(usize, usize)> {
+        break_lines(text, &glyphs(text, &[]), max_width).iter().map(|l| (l.start, l.end)).collect()
+    }
+
+    #[test]
+    fn lines_break_at_line_breaks() {
+        assert_eq!(line_ranges("ab\ncd", None), vec![(0, 3), (3, 5)]);
+        assert_eq!(line_ranges("ab\n\ncd", None), vec![(0, 3), (3, 4), (4, 6)]);
+    }
+
+    #[test]
+    fn trailing_line_break_starts_an_empty_line() {
+        assert_eq!(line_ranges("ab\n", None), vec![(0, 3), (3, 3)]);
+        assert_eq!(line_ranges("", None), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn lines_wrap_at_the_maximum_width() {
+        let text = "ab cd ef";
+        let lines = break_lines(text, &glyphs(text, &[]), Some(55.0));
+        let ranges: Vec<(usize, usize)> = lines.iter().map(|l| (l.start, l.end)).collect();
+        assert_eq!(ranges, vec![(0, 6), (6, 8)]);
+        // The space the line was wrapped at does not count
+        assert_eq!(lines[0].width, 50.0);
+        assert_eq!(line_ranges(text, None), vec![(0, 8)]);
+    }
+
+    #[test]
+    fn words_wider_than_the_maximum_get_their_own_line() {
+        assert_eq!(line_ranges("a abcdef b", Some(35.0)), vec![(0, 2), (2, 9), (9, 10)]);
+        assert_eq!(line_ranges("abcdef", Some(35.0)), vec![(0, 6)]);
+    }
+
+    fn visual_text(text: &str, levels: &[u8], rtl: bool) -> String {
+        let glyphs = glyphs(text, levels);
+        visual_order(&glyphs, rtl).into_iter().map(|i| glyphs[i].character).collect()
+    }
+
+    #[test]
+    fn left_to_right_text_keeps_its_order() {
+        assert_eq!(visual_text("abc", &[0, 0, 0], false), "abc");
+    }
+
+    #[test]
+    fn right_to_left_run_in_left_to_right_paragraph() {
+        // Uppercase letters stand in for right-to-left characters
+        assert_eq!(visual_text("ab CDE fg", &[0, 0, 0, 1, 1, 1, 0, 0, 0], false), "ab EDC fg");
+    }
+
+    #[test]
+    fn left_to_right_run_in_right_to_left_paragraph() {
+        assert_eq!(visual_text("AB cd EF", &[1, 1, 1, 2, 2, 1, 1, 1], true), "FE cd BA");
+    }
+
+    #[test]
+    fn left_to_right_line_in_right_to_left_paragraph_keeps_its_order() {
+        // Every level is even, the line is still reversed once for each level down to 1
+        assert_eq!(visual_text("abc", &[2, 2, 2], true), "abc");
+    }
+
+    #[test]
+    fn nested_levels_are_reversed_from_the_highest_level_down() {
+        assert_eq!(visual_text("ABcd12EF", &[1, 1, 2, 2, 3, 3, 1, 1], true), "FEcd21BA");
+        assert_eq!(visual_text("ab CD12 ef", &[0, 0, 0, 1, 1, 2, 2, 0, 0, 0], false), "ab 12DC ef");
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use gleam::gl::{self, Gl, GLuint};
+use glutin::dpi::{PhysicalPosition, PhysicalSize};
+use glutin::event::{WindowEvent, DeviceEvent};
+use webrender::{Renderer, RendererOptions};
+use webrender::api::*;
+use webrender::api::units::*;
+use crate::container::Group;
+use crate::event::EventTranslator;
+use crate::hit::{HitTagRegistry, ComponentPath};
+use crate::gl_state::GlStateGuard;
+use crate::offscreen::OffscreenTarget;
+use crate::resources::{ImageManager, ImageHandle};
+use crate::fonts::{FontRegistry, FontWeight, FontStyle};
+use crate::external::{ExternalTexture, ExternalTextures, ExternalTextureHandler};
+use crate::widget::*;
+use crate::error::{Error, Result};
+
+struct Notifier;
+
+impl RenderNotifier for Notifier {
+    fn clone(&self) -> Box<dyn RenderNotifier> {
+        Box::new(Notifier)
+    }
+
+    fn wake_up(&self) {
+        // The embedding application renders continuously, so there is nothing to wake up
+    }
+
+    fn new_frame_ready(&self, _: DocumentId, _scrolled: bool, _composite_needed: bool, _render_time_ns: Option<u64>) {
+        self.wake_up()
+    }
+}
+
+// Owns a webrender renderer and document and draws a component tree into it.
+// The host application forwards its window events and decides when the UI is rendered.
+pub struct EmbeddedUi<'a> {
+    gl: Rc<dyn Gl>,
+    renderer: Renderer,
+    api: RenderApi,
+    document_id: DocumentId,
+    pipeline_id: PipelineId,
+    epoch: Epoch,
+    size: DeviceIntSize,
+    scale_factor: f32,
+    txn: Transaction,
+    translator: EventTranslator,
+    hit_tags: HitTagRegistry,
+    // The component that was clicked last, it receives keyboard events first
+    focus: Option<ComponentPath>,
+    // The component a mouse button was pressed on, it receives pointer events until the button is released
+    captured: Option<ComponentPath>,
+    // The component under the pointer
+    hovered: Option<ComponentPath>,
+    ime_area: Option<LayoutRect>,
+    fonts: FontRegistry,
+    images: ImageManager,
+    external_textures: ExternalTextures,
+    external_images: HashMap<ImageKey, ExternalImageId>,
+    next_external_id: u64,
+    root: BoxedWidget<'a>
+}
+
+fn external_image_data(id: ExternalImageId) -> ImageData {
+    ImageData::External(ExternalImageData {
+        id,
+        channel_index: 0,
+        image_type: ExternalImageType::TextureHandle(TextureTarget::Default)
+    })
+}
+
+impl<'a> EmbeddedUi<'a> {
+    pub fn new(gl: Rc<dyn Gl>, size: DeviceIntSize, scale_factor: f32) -> Result<Self> {
+        let options = RendererOptions {
+            clear_color: None,
+            device_pixel_ratio: scale_factor,
+            ..RendererOptions::default()
+        };
+        // Creating the renderer compiles shaders and binds textures and buffers, the state of the application is restored afterwards
+        let (mut renderer, sender) = {
+            let _guard = GlStateGuard::new(&*gl);
+            Renderer::new(gl.clone(), Box::new(Notifier), options, None, size).map_err(Error::RendererInit)?
+        };
+
+        let external_textures = ExternalTextures::default();
+        renderer.set_external_image_handler(Box::new(ExternalTextureHandler::new(external_textures.clone())));
+
+        let api = sender.create_api();
+        let document_id = api.add_document(size, 0);
+        let pipeline_id = PipelineId(0, 0);
+
+        let mut txn = Transaction::new();
+        txn.set_document_view(DeviceIntRect::new(DeviceIntPoint::zero(), size), scale_factor);
+        txn.set_root_pipeline(pipeline_id);
+
+        Ok(EmbeddedUi {
+            gl,
+            renderer,
+            api,
+            document_id,
+            pipeline_id,
+            epoch: Epoch(0),
+            size,
+            scale_factor,
+            txn,
+            translator: EventTranslator::new(scale_factor),
+            hit_tags: HitTagRegistry::new(),
+            focus: None,
+            captured: None,
+            hovered: None,
+            ime_area: None,
+            fonts: FontRegistry::new(document_id),
+            images: ImageManager::new(),
+            external_textures,
+            external_images: HashMap::new(),
+            next_external_id: 0,
+            root: Box::new(Group::new(Vec::new()))
+        })
+    }
+
+    pub fn api(&self) -> &RenderApi {
+        &self.api
+    }
+
+    pub fn layout_size(&self) -> LayoutSize {
+        self.size.to_f32() / webrender::euclid::Scale::new(self.scale_factor)
+    }
+
+    pub fn set_root<W>(&mut self, root: W) where W: Widget + 'a {
+        self.root = Box::new(root);
+    }
+
+    // Adds a face to the font family, the first family that is loaded is used for fonts that are not found
+    pub fn load_font(&mut self, family: &str, weight: FontWeight, style: FontStyle, path: &Path) -> Result<FontKey> {
+        self.fonts.add_font_file(&self.api, family, weight, style, path)
+    }
+
+    pub fn load_font_bytes(&mut self, family: &str, weight: FontWeight, style: FontStyle, bytes: Vec<u8>) -> Result<FontKey> {
+        self.fonts.add_raw_font(&self.api, family, weight, style, bytes)
+    }
+
+    // Families that are searched in order for characters that are missing from the font of a label
+    pub fn set_font_fallbacks(&mut self, families: Vec<String>) {
+        self.fonts.set_fallbacks(families);
+    }
+
+    pub fn load_image(&mut self, path: &Path) -> Result<ImageHandle> {
+        self.images.load_file(&self.api, &mut self.txn, path)
+    }
+
+    pub fn load_image_bytes(&mut self, bytes: &[u8]) -> Result<ImageHandle> {
+        self.images.load_bytes(&self.api, &mut self.txn, bytes)
+    }
+
+    // Makes a GL texture available to webrender, so it can be shown with a Viewport3D
+    pub fn register_texture(&mut self, texture: GLuint, size: DeviceIntSize) -> ImageKey {
+        let key = self.api.generate_image_key();
+        let id = ExternalImageId(self.next_external_id);
+        self.next_external_id += 1;
+
+        self.external_textures.borrow_mut().insert(id, ExternalTexture { texture, size });
+        self.external_images.insert(key, id);
+
+        let descriptor = ImageDescriptor::new(size.width, size.height, ImageFormat::RGBA8, ImageDescriptorFlags::empty());
+        self.txn.add_image(key, descriptor, external_image_data(id), None);
+        key
+    }
+
+    // Has to be called whenever the contents of the texture change, otherwise webrender may keep showing the old frame
+    pub fn update_texture(&mut self, key: ImageKey, texture: GLuint, size: DeviceIntSize) {
+        if let Some(&id) = self.external_images.get(&key) {
+            self.external_textures.borrow_mut().insert(id, ExternalTexture { texture, size });
+
+            let descriptor = ImageDescriptor::new(size.width, size.height, ImageFormat::RGBA8, ImageDescriptorFlags::empty());
+            self.txn.update_image(key, descriptor, external_image_data(id), &DirtyRect::All);
+        }
+    }
+
+    pub fn unregister_texture(&mut self, key: ImageKey) {
+        if let Some(id) = self.external_images.remove(&key) {
+            self.txn.delete_image(key);
+            self.external_textures.borrow_mut().remove(&id);
+        }
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = DeviceIntSize::new(size.width as i32, size.height as i32);
+        self.txn.set_document_view(DeviceIntRect::new(DeviceIntPoint::zero(), self.size), self.scale_factor);
+    }
+
+    // Returns true if a component consumed the event
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(size) => self.resize(*size),
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                self.scale_factor = *scale_factor as f32;
+                self.resize(**new_inner_size);
+            }
+            _ => ()
+        }
+
+        match self.translator.translate_window_event(event) {
+            Some(input) => self.dispatch(input),
+            None => false
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) -> bool {
+        match self.translator.translate_device_event(event) {
+            Some(input) => self.dispatch(input),
+            None => false
+        }
+    }
+
+    fn dispatch(&mut self, input: WebrenderEvent) -> bool {
+        if let WebrenderEvent::Wheel { position, delta } = input {
+            self.txn.scroll(ScrollLocation::Delta(delta), WorldPoint::new(position.x, position.y));
+        }
+
+        match input {
+            WebrenderEvent::PointerPressed { position, .. } => {
+                let path = self.hit_path(position);
+                self.set_focus(path.clone());
+                self.captured = path;
+                self.dispatch_to(self.captured.clone(), input)
+            }
+            WebrenderEvent::PointerMoved { position } => {
+                let path = self.hit_path(position);
+                self.set_hovered(path.clone());
+                match &self.captured {
+                    Some(captured) => self.dispatch_to(Some(captured.clone()), input),
+                    None => self.dispatch_to(path, input)
+                }
+            }
+            WebrenderEvent::PointerReleased { .. } if self.captured.is_some() => {
+                let path = self.captured.take();
+                self.dispatch_to(path, input)
+            }
+            WebrenderEvent::CompositionUpdate { .. } | WebrenderEvent::CompositionCommit(_) => self.dispatch_to(self.focus.clone(), input),
+            // Keyboard input only goes to the whole tree while nothing has focus, so the focused widget never sees it twice
+            WebrenderEvent::KeyDown { .. } | WebrenderEvent::KeyUp { .. } | WebrenderEvent::TextInput(_) => match self.focus.clone() {
+                Some(focus) => self.dispatch_to(Some(focus), input),
+                None => self.root.broadcast_event(input)
+            },
+            _ => match input.position() {
+                Some(position) => {
+                    let path = self.hit_path(position);
+                    self.dispatch_to(path, input)
+                }
+                None => self.root.broadcast_event(input)
+            }
+        }
+    }
+
+    // glutin does not report input method compositions, hosts that get them from elsewhere forward them here.
+    // Returns true if the focused component consumed the event.
+    pub fn handle_composition(&mut self, text: String, cursor: Option<usize>) -> bool {
+        self.dispatch(WebrenderEvent::CompositionUpdate { text, cursor })
+    }
+
+    pub fn commit_composition(&mut self, text: String) -> bool {
+        self.dispatch(WebrenderEvent::CompositionCommit(text))
+    }
+
+    // Where the input method should place its candidate window, below the caret of the focused text widget.
+    // Pass it to Window::set_ime_position after each update.
+    pub fn ime_position(&self) -> Option<PhysicalPosition<i32>> {
+        self.ime_area.map(|area| {
+            PhysicalPosition::new((area.min_x() * self.scale_factor) as i32, (area.max_y() * self.scale_factor) as i32)
+        })
+    }
+
+    fn hit_path(&self, position: LayoutPoint) -> Option<ComponentPath> {
+        let point = WorldPoint::new(position.x, position.y);
+        let hit = self.api.hit_test(self.document_id, None, point, HitTestFlags::FIND_ALL);
+        self.hit_tags.resolve_hit(&hit).map(|path| path.to_vec())
+    }
+
+    fn dispatch_to(&mut self, path: Option<ComponentPath>, input: WebrenderEvent) -> bool {
+        match path {
+            Some(path) => self.root.handle_routed_event(&path, input),
+            None => false
+        }
+    }
+
+    fn set_focus(&mut self, path: Option<ComponentPath>) {
+        if path == self.focus {
+            return
+        }
+
+        let old = std::mem::replace(&mut self.focus, path);
+        self.dispatch_to(old, WebrenderEvent::FocusLost);
+        self.dispatch_to(self.focus.clone(), WebrenderEvent::FocusGained);
+    }
+
+    fn set_hovered(&mut self, path: Option<ComponentPath>) {
+        if path == self.hovered {
+            return
+        }
+
+        let old = std::mem::replace(&mut self.hovered, path);
+        self.dispatch_to(old, WebrenderEvent::PointerLeft);
+        self.dispatch_to(self.hovered.clone(), WebrenderEvent::PointerEntered);
+    }
+
+    // Updates, lays out and draws the component tree and sends the result to webrender.
+    // The frame is sent even if some widgets could not be drawn, the first of their errors is returned.
+    pub fn update(&mut self) -> Result<()> {
+        let layout_size = self.layout_size();
+        let mut uc = WebrenderUpdateContext::new(&self.api, &mut self.fonts);
+        self.root.update(&mut uc);
+        self.root.arrange(LayoutRect::new(LayoutPoint::zero(), layout_size));
+
+        let rd = WebrenderRenderData::new(SpaceAndClipInfo::root_scroll(self.pipeline_id));
+        let mut ctx = WebrenderDrawContext::new(DisplayListBuilder::new(self.pipeline_id, layout_size));
+        self.root.draw(&mut ctx, &rd);
+        self.ime_area = ctx.ime_area();
+        let (display_list, hit_tags, scroll_offsets, errors) = ctx.finalize();
+        self.hit_tags = hit_tags;
+
+        self.images.collect_garbage(&mut self.txn);
+        let mut txn = std::mem::replace(&mut self.txn, Transaction::new());
+        txn.set_display_list(self.epoch,
+                             None,
+                             layout_size,
+                             display_list,
+                             true);
+        for (id, offset) in scroll_offsets {
+            txn.scroll_node_with_id(offset, id, ScrollClamping::ToContentBounds);
+        }
+        txn.generate_frame();
+        self.api.send_transaction(self.document_id, txn);
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(())
+        }
+    }
+
+    // Renders the last frame built by webrender into the framebuffer that is currently bound.
+    // The GL state is restored afterwards, so this can be called before or after drawing with luminance.
+    pub fn render_into_current_framebuffer(&mut self) -> Result<()> {
+        let _guard = GlStateGuard::new(&*self.gl);
+        self.renderer.update();
+        self.renderer.render(self.size).map_err(Error::Render)?;
+        Ok(())
+    }
+
+    // Renders the last frame built by webrender into the texture of the target instead of the current framebuffer.
+    // The target is cleared to transparent first, so the UI can be composited onto the scene.
+    // The frame is laid out for the size of the UI, so the target has to be resized along with the window.
+    pub fn render_into_target(&mut self, target: &OffscreenTarget) -> Result<()> {
+        if target.size() != self.size {
+            return Err(Error::TargetSizeMismatch { target: target.size(), ui: self.size });
+        }
+
+        let gl = &*self.gl;
+        let _guard = GlStateGuard::new(gl);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, target.framebuffer());
+        gl.viewport(0, 0, self.size.width, self.size.height);
+        gl.disable(gl::SCISSOR_TEST);
+        gl.color_mask(true, true, true, true);
+        gl.depth_mask(true);
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear_depth(1.0);
+        gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+        self.renderer.update();
+        self.renderer.render(self.size).map_err(Error::Render)?;
+        Ok(())
+    }
+
+    pub fn deinit(self) {
+        let _guard = GlStateGuard::new(&*self.gl);
+        self.renderer.deinit();
+    }
+}
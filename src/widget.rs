@@ -1,8 +1,14 @@
 use webrender::api::*;
 use webrender::api::units::*;
-use crate::text::LayoutedText;
+use crate::text::{LayoutedText, GlyphRun, ParagraphStyle, Overflow};
 use crate::component::Component;
 use crate::state::Selector;
+use crate::hit::HitTagRegistry;
+use crate::layout::Layout;
+use crate::resources::ImageHandle;
+use crate::error::Error;
+use crate::fonts::{Font, FontMetrics, FontRegistry};
+use glutin::event::{MouseButton, VirtualKeyCode, ModifiersState};
 
 pub struct WebrenderRenderData {
     space_clip: SpaceAndClipInfo
@@ -14,31 +20,157 @@ impl WebrenderRenderData {
             space_clip
         }
     }
+
+    pub fn space_clip(&self) -> SpaceAndClipInfo {
+        self.space_clip
+    }
+}
+
+// The position a scroll frame is scrolled to
+pub type ScrollOffset = (ExternalScrollId, LayoutPoint);
+
+pub struct WebrenderDrawContext {
+    pub builder: DisplayListBuilder,
+    pub hit_tags: HitTagRegistry,
+    // Scroll frames are scrolled by their components, the offsets are applied once the display list is sent
+    scroll_offsets: Vec<ScrollOffset>,
+    // Scroll ids start at 1, id 0 belongs to the root scroll frame of the pipeline
+    next_scroll_id: u64,
+    // The caret of the focused text widget, where an input method should show its candidates
+    ime_area: Option<LayoutRect>,
+    errors: Vec<Error>
+}
+
+impl WebrenderDrawContext {
+    pub fn new(builder: DisplayListBuilder) -> Self {
+        WebrenderDrawContext {
+            builder,
+            hit_tags: HitTagRegistry::new(),
+            scroll_offsets: Vec::new(),
+            next_scroll_id: 1,
+            ime_area: None,
+            errors: Vec::new()
+        }
+    }
+
+    pub fn hit_tag(&mut self) -> ItemTag {
+        self.hit_tags.tag()
+    }
+
+    // Widgets that cannot be drawn report it here and skip drawing, the rest of the tree is still drawn
+    pub fn report(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    // Defines a scroll frame showing the content rect inside the clip rect, scrolled down and right by the offset.
+    // Items that are drawn with the returned render data move with the frame.
+    pub fn push_scroll_frame(&mut self, render_data: &WebrenderRenderData, content: LayoutRect, clip: LayoutRect, offset: LayoutVector2D) -> WebrenderRenderData {
+        let id = ExternalScrollId(self.next_scroll_id, self.builder.pipeline_id);
+        self.next_scroll_id += 1;
+        let space_clip = self.builder.define_scroll_frame(&render_data.space_clip,
+                                                          Some(id),
+                                                          content,
+                                                          clip,
+                                                          ScrollSensitivity::Script,
+                                                          LayoutVector2D::zero());
+        self.scroll_offsets.push((id, offset.to_point()));
+        WebrenderRenderData::new(space_clip)
+    }
+
+    pub fn set_ime_area(&mut self, area: LayoutRect) {
+        self.ime_area = Some(area);
+    }
+
+    pub fn ime_area(&self) -> Option<LayoutRect> {
+        self.ime_area
+    }
+
+    pub fn finalize(self) -> ((PipelineId, LayoutSize, BuiltDisplayList), HitTagRegistry, Vec<ScrollOffset>, Vec<Error>) {
+        (self.builder.finalize(), self.hit_tags, self.scroll_offsets, self.errors)
+    }
 }
 
 pub struct WebrenderUpdateContext<'a> {
     api: &'a RenderApi,
-    font: FontKey,
-    font_inst: FontInstanceKey,
-    img: ImageKey
+    fonts: &'a mut FontRegistry
 }
 
 impl<'a> WebrenderUpdateContext<'a> {
-    pub fn new(api: &'a RenderApi, font: FontKey, font_inst: FontInstanceKey, img: ImageKey) -> Self {
+    pub fn new(api: &'a RenderApi, fonts: &'a mut FontRegistry) -> Self {
         WebrenderUpdateContext {
             api,
-            font,
-            font_inst,
-            img
+            fonts
         }
     }
+
+    // Returns None if no font has been loaded yet
+    pub fn font_metrics(&mut self, font: &Font) -> Option<FontMetrics> {
+        self.fonts.instance(self.api, font).map(|i| i.metrics)
+    }
+
+    // Text has to be laid out again when one of its fonts was replaced
+    pub fn is_outdated(&self, text: &LayoutedText) -> bool {
+        !text.fonts.iter().all(|f| self.fonts.is_current(f))
+    }
+
+    // Returns None if no font has been loaded yet
+    pub fn layout_text(&mut self, text: String, font: &Font, style: &ParagraphStyle) -> Option<LayoutedText> {
+        let fonts = self.fonts.fallback_chain(self.api, font);
+        if fonts.is_empty() {
+            return None
+        }
+        Some(LayoutedText::new(text, fonts, style, self.api))
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum WebrenderEvent {
+    PointerMoved { position: LayoutPoint },
+    // The pointer moved onto or off the component
+    PointerEntered,
+    PointerLeft,
+    PointerPressed { position: LayoutPoint, button: MouseButton },
+    PointerReleased { position: LayoutPoint, button: MouseButton },
+    Wheel { position: LayoutPoint, delta: LayoutVector2D },
+    KeyDown { key: VirtualKeyCode, modifiers: ModifiersState },
+    KeyUp { key: VirtualKeyCode, modifiers: ModifiersState },
+    TextInput(char),
+    // The window gained or lost focus
+    Focused(bool),
+    // Sent to a component when it is clicked and to the previously clicked component, keyboard events go to the focused component first
+    FocusGained,
+    FocusLost,
+    // Text an input method is composing, the cursor is a byte offset into it.
+    // An empty text ends the composition, the result arrives as CompositionCommit.
+    CompositionUpdate { text: String, cursor: Option<usize> },
+    CompositionCommit(String),
+    Resized(LayoutSize)
+}
 
+impl WebrenderEvent {
+    // Pointer events are routed to the component under the cursor, everything else is broadcast
+    pub fn position(&self) -> Option<LayoutPoint> {
+        match self {
+            WebrenderEvent::PointerMoved { position } => Some(*position),
+            WebrenderEvent::PointerPressed { position, .. } => Some(*position),
+            WebrenderEvent::PointerReleased { position, .. } => Some(*position),
+            WebrenderEvent::Wheel { position, .. } => Some(*position),
+            _ => None
+        }
+    }
 }
 
+// Any component that can be drawn with webrender and positioned by a container, regardless of the lifetime of the update context
+pub trait Widget: for<'a> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> + Layout {}
+
+impl<T> Widget for T where T: for<'a> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> + Layout {}
+
+pub type BoxedWidget<'a> = Box<dyn Widget + 'a>;
+
 pub struct Rect {
+    // The position and size the rect asks for when it is laid out
+    position: LayoutPoint,
+    size: LayoutSize,
     area: LayoutRect,
     color: ColorF
 }
@@ -46,173 +178,316 @@ pub struct Rect {
 impl Rect {
     pub fn new(area: LayoutRect, color: ColorF) -> Self {
         Rect {
+            position: area.origin,
+            size: area.size,
             area,
             color
         }
     }
+
+    pub fn set_color(&mut self, color: ColorF) {
+        self.color = color;
+    }
 }
 
-impl<'a> Component<DisplayListBuilder, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> for Rect {
-    fn draw(&self, ctx: &mut DisplayListBuilder, render_data: &WebrenderRenderData) {
-        let info = CommonItemProperties::new(self.area, render_data.space_clip);
-        ctx.push_rect(&info, self.color);
+impl<'a> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> for Rect {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let mut info = CommonItemProperties::new(self.area, render_data.space_clip);
+        info.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_rect(&info, self.color);
     }
 
     fn update(&mut self, _: &mut WebrenderUpdateContext<'a>) {
 
     }
 
-    fn handle_event(&mut self, _: WebrenderEvent) {
+    fn handle_event(&mut self, _: WebrenderEvent) -> bool {
+        false
+    }
+}
 
+impl Layout for Rect {
+    fn measure(&self) -> LayoutSize {
+        self.size
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
+    }
+}
+
+// Labels that have not been arranged yet are placed at their position with the size of their text
+fn label_bounds(position: LayoutPoint, area: Option<LayoutRect>, text: &LayoutedText) -> LayoutRect {
+    area.unwrap_or_else(|| LayoutRect::new(position, text.size))
+}
+
+fn draw_text(ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData, text: &LayoutedText, runs: &[GlyphRun], bounds: LayoutRect, color: ColorF) {
+    let clip = match text.style.overflow {
+        Overflow::Visible => bounds.union(&text.text_rect(bounds)),
+        Overflow::Clip | Overflow::Ellipsis => bounds
+    };
+    let mut info = CommonItemProperties::new(clip, render_data.space_clip);
+    info.hit_info = Some(ctx.hit_tag());
+    for run in runs {
+        ctx.builder.push_text(&info, bounds, &run.glyphs, run.inst_key, color, Some(GlyphOptions::default()));
     }
 }
 
 pub struct StaticLabel {
-    text: LayoutedText,
-    glyph_instances: Vec<GlyphInstance>,
+    content: String,
+    font: Font,
+    style: ParagraphStyle,
+    text: Option<LayoutedText>,
+    glyph_runs: Vec<GlyphRun>,
     position: LayoutPoint,
+    // The box the text is aligned in, set when the label is arranged
+    area: Option<LayoutRect>,
     color: ColorF
 }
 
 impl StaticLabel {
-    pub fn new(text: LayoutedText, position: LayoutPoint, color: ColorF) -> Self {
-        let offset = text.dimensions.iter().fold(0.0, |y, &g| {
-            let dy = g.height as f32;
-            f32::max(y, dy)
-        });
-
-        let glyph_instances: Vec<GlyphInstance> = text
-            .indices
-            .iter()
-            .zip(&text.dimensions)
-            .scan(position.x, |x, (index, dim)| {
-                let tx = *x;
-                *x = tx + dim.advance;
-
-                Some(GlyphInstance {
-                    index: *index,
-                    point: LayoutPoint::new(tx, position.y + offset)
-                })
-            }).collect();
+    pub fn new(content: &str, font: Font, position: LayoutPoint, color: ColorF) -> Self {
+        StaticLabel::with_style(content, font, ParagraphStyle::default(), position, color)
+    }
 
+    pub fn with_style(content: &str, font: Font, style: ParagraphStyle, position: LayoutPoint, color: ColorF) -> Self {
         StaticLabel {
-            text,
+            content: content.to_string(),
+            font,
+            style,
+            text: None,
             position,
-            glyph_instances,
+            area: None,
+            glyph_runs: Vec::new(),
             color
         }
     }
+
+    pub fn set_color(&mut self, color: ColorF) {
+        self.color = color;
+    }
+}
+
+impl Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'_>, WebrenderEvent> for StaticLabel {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let text = match &self.text {
+            Some(text) => text,
+            None => return ctx.report(Error::NotUpdated("StaticLabel"))
+        };
+        draw_text(ctx, render_data, text, &self.glyph_runs, label_bounds(self.position, self.area, text), self.color);
+    }
+
+    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'_>) {
+        let outdated = match &self.text {
+            Some(text) => ctx.is_outdated(text),
+            None => true
+        };
+        if outdated {
+            self.text = ctx.layout_text(self.content.clone(), &self.font, &self.style);
+            if let Some(text) = &self.text {
+                self.glyph_runs = text.glyph_runs(label_bounds(self.position, self.area, text));
+            }
+        }
+    }
+
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
 }
 
-impl Component<DisplayListBuilder, WebrenderRenderData, WebrenderUpdateContext<'_>, WebrenderEvent> for StaticLabel {
-    fn draw(&self, ctx: &mut DisplayListBuilder, render_data: &WebrenderRenderData) {
-        let area = LayoutRect::new(self.position, self.text.size);
-        let mut info = CommonItemProperties::new(area, render_data.space_clip);
-        info.hit_info = Some((0, 1));
-        ctx.push_text(&info, area, &self.glyph_instances, self.text.inst_key, self.color, Some(GlyphOptions::default()));
+impl Layout for StaticLabel {
+    fn measure(&self) -> LayoutSize {
+        self.text.as_ref().map(|t| t.size).unwrap_or_else(LayoutSize::zero)
     }
 
-    fn update(&mut self, _ctx: &mut WebrenderUpdateContext<'_>) {
+    fn position(&self) -> LayoutPoint {
+        self.position
     }
 
-    fn handle_event(&mut self, _event: WebrenderEvent) {
-        //TODO
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = Some(bounds);
+        if let Some(text) = &self.text {
+            self.glyph_runs = text.glyph_runs(label_bounds(self.position, self.area, text));
+        }
     }
 }
 
 pub struct DynamicLabel<'a, S> where S: Into<String> {
     text_selector: Selector<'a, S>,
+    font: Font,
+    style: ParagraphStyle,
     text: Option<LayoutedText>,
-    glyph_instances: Vec<GlyphInstance>,
+    glyph_runs: Vec<GlyphRun>,
     position: LayoutPoint,
+    // The box the text is aligned in, set when the label is arranged
+    area: Option<LayoutRect>,
     color: ColorF
 }
 
 impl<'a, S> DynamicLabel<'a, S> where S: Into<String> {
-    pub fn new(text_selector: Selector<'a, S>, position: LayoutPoint, color: ColorF) -> Self {
+    pub fn new(text_selector: Selector<'a, S>, font: Font, position: LayoutPoint, color: ColorF) -> Self {
+        DynamicLabel::with_style(text_selector, font, ParagraphStyle::default(), position, color)
+    }
+
+    pub fn with_style(text_selector: Selector<'a, S>, font: Font, style: ParagraphStyle, position: LayoutPoint, color: ColorF) -> Self {
         DynamicLabel {
             text_selector,
+            font,
+            style,
             position,
-            glyph_instances: Vec::new(),
+            area: None,
+            glyph_runs: Vec::new(),
             text: None,
             color
         }
     }
 }
 
-impl<'a, 'b, S> Component<DisplayListBuilder, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for DynamicLabel<'a, S> where S: Into<String> {
-    fn draw(&self, ctx: &mut DisplayListBuilder, render_data: &WebrenderRenderData) {
-        let text = self.text.as_ref().unwrap();
-        let area = LayoutRect::new(self.position, text.size);
-        let mut info = CommonItemProperties::new(area, render_data.space_clip);
-        info.hit_info = Some((0, 1));
-        ctx.push_text(&info, area, &self.glyph_instances, text.inst_key, self.color, Some(GlyphOptions::default()));
+impl<'a, 'b, S> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'b>, WebrenderEvent> for DynamicLabel<'a, S> where S: Into<String> {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let text = match &self.text {
+            Some(text) => text,
+            None => return ctx.report(Error::NotUpdated("DynamicLabel"))
+        };
+        draw_text(ctx, render_data, text, &self.glyph_runs, label_bounds(self.position, self.area, text), self.color);
     }
 
     fn update(&mut self, ctx: &mut WebrenderUpdateContext<'b>) {
         let new_text = (self.text_selector)().into();
 
         if let Some(old_text) = &self.text {
-            if old_text.text == new_text {
+            if old_text.text == new_text && !ctx.is_outdated(old_text) {
                 return
             }
         }
 
-        let text = LayoutedText::new(new_text, ctx.font, ctx.font_inst, ctx.api);
-
-        let offset = text.dimensions.iter().fold(0.0, |y, &g| {
-            let dy = g.height as f32;
-            f32::max(y, dy)
-        });
+        self.text = ctx.layout_text(new_text, &self.font, &self.style);
+        if let Some(text) = &self.text {
+            self.glyph_runs = text.glyph_runs(label_bounds(self.position, self.area, text));
+        }
+    }
 
-        self.glyph_instances = text
-            .indices
-            .iter()
-            .zip(&text.dimensions)
-            .scan(self.position.x, |x, (index, dim)| {
-                let tx = *x;
-                *x = tx + dim.advance;
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+}
 
-                Some(GlyphInstance {
-                    index: *index,
-                    point: LayoutPoint::new(tx, self.position.y + offset)
-                })
-            }).collect();
+impl<'a, S> Layout for DynamicLabel<'a, S> where S: Into<String> {
+    fn measure(&self) -> LayoutSize {
+        self.text.as_ref().map(|t| t.size).unwrap_or_else(LayoutSize::zero)
+    }
 
-        self.text = Some(text);
+    fn position(&self) -> LayoutPoint {
+        self.position
     }
 
-    fn handle_event(&mut self, _event: WebrenderEvent) {
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = Some(bounds);
+        if let Some(text) = &self.text {
+            self.glyph_runs = text.glyph_runs(label_bounds(self.position, self.area, text));
+        }
     }
 }
 
 pub struct ImageDisplay {
+    // The position and size it asks for when it is laid out
     position: LayoutPoint,
     size: LayoutSize,
-    img: Option<ImageKey>
+    area: LayoutRect,
+    img: ImageHandle
 }
 
 impl ImageDisplay {
-    pub fn new(position: LayoutPoint, size: LayoutSize) -> Self {
+    pub fn new(img: ImageHandle, position: LayoutPoint, size: LayoutSize) -> Self {
         ImageDisplay {
             position,
             size,
-            img: None
+            area: LayoutRect::new(position, size),
+            img
         }
     }
 }
 
-impl<'a> Component<DisplayListBuilder, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> for ImageDisplay {
-    fn draw(&self, ctx: &mut DisplayListBuilder, render_data: &WebrenderRenderData) {
-        let bounds = LayoutRect::new(self.position, self.size);
-        let data = CommonItemProperties::new(bounds, render_data.space_clip);
-        ctx.push_image(&data, bounds, ImageRendering::Auto, AlphaType::Alpha, self.img.unwrap(), ColorF::WHITE);
+impl<'a> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> for ImageDisplay {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let bounds = self.area;
+        let mut data = CommonItemProperties::new(bounds, render_data.space_clip);
+        data.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_image(&data, bounds, ImageRendering::Auto, AlphaType::PremultipliedAlpha, self.img.key(), ColorF::WHITE);
+    }
+
+    fn update(&mut self, _ctx: &mut WebrenderUpdateContext<'a>) {
+    }
+
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+}
+
+impl Layout for ImageDisplay {
+    fn measure(&self) -> LayoutSize {
+        self.size
+    }
+
+    fn position(&self) -> LayoutPoint {
+        self.position
+    }
+
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
+    }
+}
+
+// Shows a texture registered with EmbeddedUi::register_texture, e.g. a 3D scene rendered with luminance
+pub struct Viewport3D {
+    // The position and size it asks for when it is laid out
+    position: LayoutPoint,
+    size: LayoutSize,
+    area: LayoutRect,
+    image: ImageKey
+}
+
+impl Viewport3D {
+    pub fn new(image: ImageKey, position: LayoutPoint, size: LayoutSize) -> Self {
+        Viewport3D {
+            position,
+            size,
+            area: LayoutRect::new(position, size),
+            image
+        }
+    }
+}
+
+impl<'a> Component<WebrenderDrawContext, WebrenderRenderData, WebrenderUpdateContext<'a>, WebrenderEvent> for Viewport3D {
+    fn draw(&self, ctx: &mut WebrenderDrawContext, render_data: &WebrenderRenderData) {
+        let bounds = self.area;
+        let mut data = CommonItemProperties::new(bounds, render_data.space_clip);
+        data.hit_info = Some(ctx.hit_tag());
+        ctx.builder.push_image(&data, bounds, ImageRendering::Auto, AlphaType::PremultipliedAlpha, self.image, ColorF::WHITE);
+    }
+
+    fn update(&mut self, _ctx: &mut WebrenderUpdateContext<'a>) {
+    }
+
+    fn handle_event(&mut self, _event: WebrenderEvent) -> bool {
+        false
+    }
+}
+
+impl Layout for Viewport3D {
+    fn measure(&self) -> LayoutSize {
+        self.size
     }
 
-    fn update(&mut self, ctx: &mut WebrenderUpdateContext<'a>) {
-        self.img = Some(ctx.img);
+    fn position(&self) -> LayoutPoint {
+        self.position
     }
 
-    fn handle_event(&mut self, event: WebrenderEvent) {
+    fn arrange(&mut self, bounds: LayoutRect) {
+        self.area = bounds;
     }
 }
\ No newline at end of file